@@ -0,0 +1,388 @@
+use core::{error::Error, fmt::Write, ops::RangeInclusive};
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+/// Board built when `BOARD` is not set
+pub const DEFAULT_BOARD: &str = "default";
+
+/// GPIOs of RP2350A and RP2040
+const GPIO_COUNT: u8 = 30;
+/// GPIOs with ADC input
+const ADC_PINS: RangeInclusive<u8> = 26..=29;
+/// PWM slices of RP2350A and RP2040. GPIO n is on slice `n / 2 % PWM_SLICES`, channel A if even
+const PWM_SLICES: u8 = 8;
+
+/// Buttons in firmware `ButtonId` order, and whether they are required
+const BUTTONS: [(&str, bool); 11] = [
+    ("button1", true),
+    ("button2", true),
+    ("button3", true),
+    ("button4", true),
+    ("fx1", true),
+    ("fx2", true),
+    ("start", true),
+    ("aux1", false),
+    ("aux2", false),
+    ("aux3", false),
+    ("aux4", false),
+];
+
+/// Lamps in firmware `LedPinout` order. All optional
+const LEDS: [&str; 7] = [
+    "button1", "button2", "button3", "button4", "fx1", "fx2", "start",
+];
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Low,
+    High,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Pull {
+    None,
+    Up,
+    Down,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Board {
+    buttons: Group,
+    knobs: Knobs,
+    #[serde(default)]
+    leds: Group,
+}
+
+/// Pins of a control group with settings shared by them
+#[derive(Deserialize, Default)]
+struct Group {
+    /// Level of pressed button or lit lamp
+    active: Option<Level>,
+    /// Internal resistor of button pins
+    pull: Option<Pull>,
+    #[serde(flatten)]
+    pins: BTreeMap<String, PinDef>,
+}
+
+/// GPIO number, or table overriding settings of the group
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PinDef {
+    Pin(u8),
+    Config(PinConfig),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinConfig {
+    pin: u8,
+    active: Option<Level>,
+    pull: Option<Pull>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Knobs {
+    left: u8,
+    right: u8,
+}
+
+#[derive(Clone, Copy)]
+struct Pin {
+    gpio: u8,
+    active: Level,
+    pull: Pull,
+}
+
+/// Generate pinout macros of board definition `src`.
+///
+/// `input_pinout!(p)` builds `InputPinout` and `led_pinout!(p)` builds `LedPinout` from peripherals `p`.
+/// Fails on missing buttons, pins that do not exist or are used twice,
+/// knobs without ADC and lamps sharing a PWM channel.
+pub fn generate(src: &str) -> Result<String, Box<dyn Error>> {
+    let board: Board = toml::from_str(src)?;
+
+    let buttons = resolve_group(
+        "buttons",
+        &board.buttons,
+        &BUTTONS,
+        Level::Low,
+        Some(Pull::Up),
+    )?;
+    let leds = resolve_group(
+        "leds",
+        &board.leds,
+        &LEDS.map(|name| (name, false)),
+        Level::High,
+        None,
+    )?;
+
+    let mut used = BTreeMap::new();
+    let mut claim = |gpio: u8, name: String| -> Result<(), Box<dyn Error>> {
+        if gpio >= GPIO_COUNT {
+            return Err(format!("{name} is on GPIO {gpio}, which does not exist").into());
+        }
+        if let Some(other) = used.insert(gpio, name.clone()) {
+            return Err(format!("GPIO {gpio} is used by both {other} and {name}").into());
+        }
+        Ok(())
+    };
+    for ((name, _), pin) in BUTTONS.iter().zip(&buttons) {
+        if let Some(pin) = pin {
+            claim(pin.gpio, format!("buttons.{name}"))?;
+        }
+    }
+    for (name, gpio) in [("left", board.knobs.left), ("right", board.knobs.right)] {
+        claim(gpio, format!("knobs.{name}"))?;
+        if !ADC_PINS.contains(&gpio) {
+            return Err(format!(
+                "knobs.{name} is on GPIO {gpio}, but only GPIO {}-{} have ADC",
+                ADC_PINS.start(),
+                ADC_PINS.end()
+            )
+            .into());
+        }
+    }
+    for (name, pin) in LEDS.iter().zip(&leds) {
+        if let Some(pin) = pin {
+            claim(pin.gpio, format!("leds.{name}"))?;
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated from board definition. Do not edit.")?;
+    write_input_pinout(&mut out, &buttons, &board.knobs)?;
+    write_led_pinout(&mut out, &leds)?;
+    Ok(out)
+}
+
+/// Resolve pins of group in `names` order, applying group settings
+fn resolve_group(
+    group_name: &str,
+    group: &Group,
+    names: &[(&str, bool)],
+    default_active: Level,
+    default_pull: Option<Pull>,
+) -> Result<Vec<Option<Pin>>, Box<dyn Error>> {
+    if let Some(name) = group
+        .pins
+        .keys()
+        .find(|key| !names.iter().any(|(name, _)| name == key))
+    {
+        return Err(format!("unknown control {group_name}.{name}").into());
+    }
+    if default_pull.is_none() && group.pull.is_some() {
+        return Err(format!("{group_name} has no pull setting").into());
+    }
+
+    let active = group.active.unwrap_or(default_active);
+    let pull = group.pull.or(default_pull).unwrap_or(Pull::None);
+    names
+        .iter()
+        .map(|(name, required)| {
+            let pin = match group.pins.get(*name) {
+                Some(PinDef::Pin(gpio)) => Pin {
+                    gpio: *gpio,
+                    active,
+                    pull,
+                },
+
+                Some(PinDef::Config(config)) => {
+                    if default_pull.is_none() && config.pull.is_some() {
+                        return Err(format!("{group_name}.{name} has no pull setting").into());
+                    }
+
+                    Pin {
+                        gpio: config.pin,
+                        active: config.active.unwrap_or(active),
+                        pull: config.pull.unwrap_or(pull),
+                    }
+                }
+
+                None if *required => {
+                    return Err(format!("{group_name}.{name} is not assigned").into());
+                }
+                None => return Ok(None),
+            };
+
+            Ok(Some(pin))
+        })
+        .collect()
+}
+
+fn write_input_pinout(
+    out: &mut String,
+    buttons: &[Option<Pin>],
+    knobs: &Knobs,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "macro_rules! input_pinout {{")?;
+    writeln!(out, "    ($p:ident) => {{")?;
+    writeln!(out, "        $crate::input::config::InputPinout {{")?;
+    writeln!(out, "            buttons: [")?;
+    for pin in buttons {
+        match pin {
+            Some(pin) => writeln!(
+                out,
+                "                Some($crate::input::config::ButtonPin {{ \
+                pin: $p.PIN_{}.into(), \
+                active: ::embassy_rp::gpio::Level::{:?}, \
+                pull: ::embassy_rp::gpio::Pull::{:?} }}),",
+                pin.gpio, pin.active, pin.pull
+            )?,
+            None => writeln!(out, "                None,")?,
+        }
+    }
+    writeln!(out, "            ],")?;
+    writeln!(out, "            knobs: [")?;
+    for gpio in [knobs.left, knobs.right] {
+        writeln!(
+            out,
+            "                ::embassy_rp::adc::Channel::new_pin($p.PIN_{gpio}, ::embassy_rp::gpio::Pull::None),"
+        )?;
+    }
+    writeln!(out, "            ],")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }};")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "pub(crate) use input_pinout;")?;
+    Ok(())
+}
+
+/// Lamps on the same PWM slice are driven by one `Pwm`
+fn write_led_pinout(out: &mut String, leds: &[Option<Pin>]) -> Result<(), Box<dyn Error>> {
+    let mut slices = BTreeMap::<u8, [Option<(usize, Pin)>; 2]>::new();
+    for (i, pin) in leds.iter().enumerate() {
+        let Some(pin) = pin else {
+            continue;
+        };
+
+        let slice = pin.gpio / 2 % PWM_SLICES;
+        let channel = &mut slices.entry(slice).or_default()[(pin.gpio % 2) as usize];
+        if let Some((other, other_pin)) = channel {
+            return Err(format!(
+                "leds.{} (GPIO {}) and leds.{} (GPIO {}) share PWM slice {slice} channel {}",
+                LEDS[*other],
+                other_pin.gpio,
+                LEDS[i],
+                pin.gpio,
+                if pin.gpio % 2 == 0 { 'A' } else { 'B' },
+            )
+            .into());
+        }
+        *channel = Some((i, *pin));
+    }
+
+    writeln!(out, "macro_rules! led_pinout {{")?;
+    writeln!(out, "    ($p:ident) => {{{{")?;
+    for (slice, [a, b]) in &slices {
+        let lamp = |channel: &Option<(usize, Pin)>| match channel {
+            Some((i, _)) => format!("lamp{i}"),
+            None => "_".to_string(),
+        };
+        let invert = |channel: &Option<(usize, Pin)>| matches!(channel, Some((_, pin)) if pin.active == Level::Low);
+        let (constructor, pins) = match (a, b) {
+            (Some((_, a)), Some((_, b))) => (
+                "new_output_ab",
+                format!("$p.PIN_{}, $p.PIN_{}", a.gpio, b.gpio),
+            ),
+            (Some((_, a)), None) => ("new_output_a", format!("$p.PIN_{}", a.gpio)),
+            (None, Some((_, b))) => ("new_output_b", format!("$p.PIN_{}", b.gpio)),
+            (None, None) => continue,
+        };
+
+        writeln!(
+            out,
+            "        let ({}, {}) = ::embassy_rp::pwm::Pwm::{constructor}($p.PWM_SLICE{slice}, {pins}, \
+            $crate::led::pwm_config({}, {})).split();",
+            lamp(a),
+            lamp(b),
+            invert(a),
+            invert(b),
+        )?;
+    }
+
+    let lamps = (0..leds.len())
+        .map(|i| {
+            if leds[i].is_some() {
+                format!("lamp{i}")
+            } else {
+                "None".to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    writeln!(out, "        $crate::led::LedPinout([{lamps}])")?;
+    writeln!(out, "    }}}};")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "pub(crate) use led_pinout;")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    const BOARDS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../firmware/boards");
+
+    const BUTTONS: &str = r#"
+        [buttons]
+        button1 = 0
+        button2 = 1
+        button3 = 2
+        button4 = 3
+        fx1 = 4
+        fx2 = 5
+        start = { pin = 6, active = "high", pull = "down" }
+    "#;
+
+    #[test]
+    fn boards_test() {
+        for entry in fs::read_dir(BOARDS_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            let src = fs::read_to_string(&path).unwrap();
+            if let Err(err) = crate::generate(&src) {
+                panic!("{}: {err}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn pin_conflict_test() {
+        let src = format!("{BUTTONS}\n[knobs]\nleft = 26\nright = 27\n[leds]\nstart = 4\n");
+        assert_eq!(
+            crate::generate(&src).unwrap_err().to_string(),
+            "GPIO 4 is used by both buttons.fx1 and leds.start"
+        );
+
+        let src = format!("{BUTTONS}\n[knobs]\nleft = 26\nright = 27\n[leds]\nfx1 = 8\nfx2 = 24\n");
+        assert_eq!(
+            crate::generate(&src).unwrap_err().to_string(),
+            "leds.fx1 (GPIO 8) and leds.fx2 (GPIO 24) share PWM slice 4 channel A"
+        );
+    }
+
+    #[test]
+    fn invalid_pin_test() {
+        let src = format!("{BUTTONS}\n[knobs]\nleft = 7\nright = 27\n");
+        assert_eq!(
+            crate::generate(&src).unwrap_err().to_string(),
+            "knobs.left is on GPIO 7, but only GPIO 26-29 have ADC"
+        );
+
+        let src = format!("{BUTTONS}\naux1 = 30\n[knobs]\nleft = 26\nright = 27\n");
+        assert_eq!(
+            crate::generate(&src).unwrap_err().to_string(),
+            "buttons.aux1 is on GPIO 30, which does not exist"
+        );
+
+        let src = "[buttons]\nbutton1 = 0\n[knobs]\nleft = 26\nright = 27\n";
+        assert_eq!(
+            crate::generate(src).unwrap_err().to_string(),
+            "buttons.button2 is not assigned"
+        );
+    }
+}
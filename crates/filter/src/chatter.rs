@@ -0,0 +1,116 @@
+/// Chatter of a switch transition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounce {
+    /// Times the raw state returned to the level before the transition
+    pub bounces: u16,
+    /// Time from the transition edge to the last bounce edge
+    pub duration_ms: u16,
+}
+
+/// Chatter meter for button.
+///
+/// Measures raw edges the debouncer absorbs.
+/// A transition starts at a raw edge and ends when the raw state is stable for `SETTLE_MS`.
+/// Extra edges in between are bounces.
+pub struct ChatterMeter<const SETTLE_MS: u16> {
+    last_raw_value: bool,
+    /// Extra edges in current transition, `None` if settled
+    edges: Option<u16>,
+    duration_ms: u16,
+    /// Time since last edge
+    stable_ms: u16,
+}
+
+impl<const SETTLE_MS: u16> ChatterMeter<SETTLE_MS> {
+    pub const fn new(initial: bool) -> Self {
+        Self {
+            last_raw_value: initial,
+            edges: None,
+            duration_ms: 0,
+            stable_ms: 0,
+        }
+    }
+
+    /// Returns [`Bounce`] when a transition with bounces settles.
+    pub fn update(&mut self, raw_state: bool, elapsed_ms: u16) -> Option<Bounce> {
+        let edge = raw_state != self.last_raw_value;
+        self.last_raw_value = raw_state;
+
+        let Some(edges) = &mut self.edges else {
+            if edge {
+                // Transition started
+                self.edges = Some(0);
+                self.duration_ms = 0;
+                self.stable_ms = 0;
+            }
+
+            return None;
+        };
+
+        self.duration_ms = self.duration_ms.saturating_add(elapsed_ms);
+        if edge {
+            *edges = edges.saturating_add(1);
+            self.stable_ms = 0;
+            return None;
+        }
+
+        self.stable_ms = self.stable_ms.saturating_add(elapsed_ms);
+        if self.stable_ms < SETTLE_MS {
+            return None;
+        }
+
+        let edges = self.edges.take().unwrap_or_default();
+        if edges == 0 {
+            return None;
+        }
+
+        Some(Bounce {
+            bounces: edges.div_ceil(2),
+            duration_ms: self.duration_ms - self.stable_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chatter::{Bounce, ChatterMeter};
+
+    #[test]
+    fn clean_transition_test() {
+        let mut meter = ChatterMeter::<3>::new(false);
+        for raw_state in [true, true, true, true, false, false, false, false] {
+            assert_eq!(meter.update(raw_state, 1), None);
+        }
+    }
+
+    #[test]
+    fn bounce_test() {
+        // (raw_state, bounce)
+        let input_seq = [
+            // Release edge
+            (false, None),
+            // Bounces
+            (true, None),
+            (false, None),
+            (false, None),
+            (true, None),
+            (false, None),
+            // Settling
+            (false, None),
+            (false, None),
+            (
+                false,
+                Some(Bounce {
+                    bounces: 2,
+                    duration_ms: 5,
+                }),
+            ),
+            (false, None),
+        ];
+
+        let mut meter = ChatterMeter::<3>::new(true);
+        for (i, (raw_state, bounce)) in input_seq.into_iter().enumerate() {
+            assert_eq!(meter.update(raw_state, 1), bounce, "step {i}");
+        }
+    }
+}
@@ -0,0 +1,213 @@
+/// Buttons and combos pressed after combo resolution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboOutput {
+    /// Buttons passed through
+    pub buttons: u32,
+    /// Bit mask of active combos
+    pub combos: u32,
+}
+
+/// Combo resolver for buttons.
+///
+/// Combos are button masks. A pressed button of any combo is held back for the combo window.
+/// When all buttons of a combo are pressed within the window, the combo becomes active and
+/// its buttons are suppressed until released. A combo stays active while all its buttons are held.
+/// When the window ends without a combo, a button outside combos is pressed,
+/// or a held back button is released, held back buttons are passed through.
+/// Buttons released while held back are sent as a tap for `tap_ms`.
+pub struct ComboResolver {
+    last_pressed: u32,
+    /// Buttons held back until a combo completes or the window ends
+    waiting: u32,
+    waiting_ms: u16,
+    /// Held back buttons released within window
+    tapping: u32,
+    tapping_ms: u16,
+    /// Buttons of active combos, suppressed until released
+    consumed: u32,
+    active: u32,
+}
+
+impl ComboResolver {
+    pub const fn new() -> Self {
+        Self {
+            last_pressed: 0,
+            waiting: 0,
+            waiting_ms: 0,
+            tapping: 0,
+            tapping_ms: 0,
+            consumed: 0,
+            active: 0,
+        }
+    }
+
+    /// Buttons are held back or tapping, time must keep advancing
+    pub fn pending(&self) -> bool {
+        self.waiting != 0 || self.tapping != 0
+    }
+
+    pub fn update(
+        &mut self,
+        pressed: u32,
+        elapsed_ms: u16,
+        combos: &[u32],
+        window_ms: u16,
+        tap_ms: u16,
+    ) -> ComboOutput {
+        let new = pressed & !self.last_pressed;
+        self.last_pressed = pressed;
+
+        if self.tapping != 0 {
+            self.tapping_ms = self.tapping_ms.saturating_add(elapsed_ms);
+            if self.tapping_ms >= tap_ms {
+                self.tapping = 0;
+            }
+        }
+
+        // Combo is released when any of its buttons is released
+        for (i, combo) in combos.iter().enumerate() {
+            if pressed & combo != *combo {
+                self.active &= !(1 << i);
+            }
+        }
+        self.consumed &= pressed;
+
+        let members = combos.iter().fold(0, |members, combo| members | combo);
+        if self.waiting == 0 {
+            self.waiting_ms = 0;
+        } else {
+            self.waiting_ms = self.waiting_ms.saturating_add(elapsed_ms);
+        }
+
+        let interrupted = new & !members != 0;
+        self.waiting |= new & members & !self.consumed;
+
+        let released = self.waiting & !pressed;
+        if released != 0 || interrupted {
+            self.flush(released);
+        } else if self.waiting != 0 {
+            // Keep waiting while a larger combo can still complete
+            let growing = combos.iter().any(|combo| {
+                *combo != 0 && *combo & self.waiting == self.waiting && *combo != self.waiting
+            });
+
+            if !growing || self.waiting_ms >= window_ms {
+                match largest_combo(combos, self.waiting) {
+                    Some(i) => {
+                        self.active |= 1 << i;
+                        self.consumed |= combos[i];
+                        self.waiting &= !combos[i];
+                    }
+
+                    // Window ended, or no combo can complete
+                    None => self.flush(0),
+                }
+            }
+        }
+
+        ComboOutput {
+            buttons: (pressed & !self.waiting & !self.consumed) | self.tapping,
+            combos: self.active,
+        }
+    }
+
+    /// Pass held back buttons through, released ones as tap
+    fn flush(&mut self, released: u32) {
+        if released != 0 {
+            self.tapping |= released;
+            self.tapping_ms = 0;
+        }
+        self.waiting = 0;
+    }
+}
+
+impl Default for ComboResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Index of combo with most buttons, all in `pressed`
+fn largest_combo(combos: &[u32], pressed: u32) -> Option<usize> {
+    combos
+        .iter()
+        .enumerate()
+        .filter(|(_, combo)| **combo != 0 && **combo & pressed == **combo)
+        .max_by_key(|(_, combo)| combo.count_ones())
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combo::{ComboOutput, ComboResolver};
+
+    const WINDOW: u16 = 3;
+    const TAP: u16 = 2;
+    const COMBOS: [u32; 2] = [0b011, 0b111];
+
+    fn run(seq: &[(u32, u32, u32)]) {
+        let mut resolver = ComboResolver::new();
+        for (i, (pressed, buttons, combos)) in seq.iter().enumerate() {
+            assert_eq!(
+                resolver.update(*pressed, 1, &COMBOS, WINDOW, TAP),
+                ComboOutput {
+                    buttons: *buttons,
+                    combos: *combos
+                },
+                "step {i}"
+            );
+        }
+    }
+
+    #[test]
+    fn combo_test() {
+        // (pressed, buttons, combos)
+        run(&[
+            (0b001, 0, 0),
+            (0b011, 0, 0),
+            // Largest combo completes
+            (0b111, 0, 0b10),
+            (0b111, 0, 0b10),
+            // Released combo keeps buttons suppressed
+            (0b110, 0, 0),
+            (0b000, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn window_test() {
+        run(&[
+            (0b001, 0, 0),
+            (0b011, 0, 0),
+            (0b011, 0, 0),
+            // Window ends, smaller combo fires
+            (0b011, 0, 0b01),
+            (0b011, 0, 0b01),
+            (0b000, 0, 0),
+        ]);
+
+        run(&[
+            (0b100, 0, 0),
+            (0b100, 0, 0),
+            (0b100, 0, 0),
+            // Window ends without combo, passed through
+            (0b100, 0b100, 0),
+            (0b000, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn interrupt_test() {
+        run(&[
+            (0b0001, 0, 0),
+            // Button outside combos passes held back buttons
+            (0b1001, 0b1001, 0),
+            (0b0000, 0, 0),
+            (0b0001, 0, 0),
+            // Released while held back is a tap
+            (0b0000, 0b0001, 0),
+            (0b0000, 0b0001, 0),
+            (0b0000, 0, 0),
+        ]);
+    }
+}
@@ -0,0 +1,136 @@
+/// A filter to skip writing reports the host already has.
+///
+/// Reports equal to the last written one are suppressed,
+/// except reports carrying relative values (e.g. mouse movement) which are applied on every write.
+pub struct ReportDedup<T> {
+    last: Option<T>,
+}
+
+impl<T: PartialEq + Clone> ReportDedup<T> {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Returns `true` if `report` should be written.
+    /// `relative` tells if `report` contains non zero relative values.
+    pub fn check(&mut self, report: &T, relative: bool) -> bool {
+        if !relative && self.last.as_ref() == Some(report) {
+            return false;
+        }
+
+        self.last = Some(report.clone());
+        true
+    }
+
+    /// Handle failed write of `report`.
+    /// Returns the report to write again if no newer report was checked since,
+    /// as unchanged inputs would not produce it again.
+    pub fn failed(&mut self, report: T) -> Option<T> {
+        if self.last.as_ref() == Some(&report) {
+            return Some(report);
+        }
+
+        None
+    }
+
+    /// Last report passed by [`check`](Self::check)
+    #[inline]
+    pub fn last(&self) -> Option<&T> {
+        self.last.as_ref()
+    }
+
+    /// Forget last report, so next report is always written.
+    /// Use when the last write did not reach the host.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+impl<T: PartialEq + Clone> Default for ReportDedup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dedup::ReportDedup;
+
+    #[test]
+    fn absolute_report_test() {
+        // (report, should_write)
+        let report_seq = [
+            // First report is always written
+            (0, true),
+            // Unchanged
+            (0, false),
+            (0, false),
+            // Changed
+            (1, true),
+            (1, false),
+            (2, true),
+            // Back to neutral
+            (0, true),
+            (0, false),
+        ];
+
+        let mut dedup = ReportDedup::new();
+        for (report, should_write) in report_seq {
+            assert_eq!(dedup.check(&report, false), should_write);
+        }
+    }
+
+    #[test]
+    fn relative_report_test() {
+        // (movement, should_write)
+        let report_seq = [
+            (0, true),
+            (0, false),
+            // Same movement keeps streaming
+            (5, true),
+            (5, true),
+            (5, true),
+            // Stop movement once
+            (0, true),
+            (0, false),
+        ];
+
+        let mut dedup = ReportDedup::new();
+        for (movement, should_write) in report_seq {
+            assert_eq!(dedup.check(&movement, movement != 0), should_write);
+        }
+    }
+
+    #[test]
+    fn reset_test() {
+        let mut dedup = ReportDedup::new();
+        assert!(dedup.check(&1, false));
+        assert!(!dedup.check(&1, false));
+
+        // Rewrite after failed write
+        dedup.reset();
+        assert!(dedup.check(&1, false));
+        assert!(!dedup.check(&1, false));
+    }
+
+    #[test]
+    fn failed_test() {
+        let mut dedup = ReportDedup::new();
+        assert!(dedup.check(&1, false));
+
+        // Release fails while inputs stay neutral
+        assert!(dedup.check(&0, false));
+        assert_eq!(dedup.failed(0), Some(0));
+        assert!(!dedup.check(&0, false));
+        // Retry fails again
+        assert_eq!(dedup.failed(0), Some(0));
+
+        // Newer report replaces failed one
+        assert!(dedup.check(&2, false));
+        assert!(dedup.check(&3, false));
+        assert_eq!(dedup.failed(2), None);
+        assert!(!dedup.check(&3, false));
+    }
+}
@@ -0,0 +1,134 @@
+/// Summary of recorded values
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct HistogramSummary {
+    pub count: u32,
+    pub min: u32,
+    pub avg: u32,
+    pub max: u32,
+    /// 99th percentile, rounded up to bucket boundary
+    pub p99: u32,
+}
+
+/// Fixed width bucket histogram.
+/// Values above the last bucket are counted in the last bucket.
+pub struct Histogram<const BUCKETS: usize, const WIDTH: u32> {
+    buckets: [u32; BUCKETS],
+    count: u32,
+    sum: u64,
+    min: u32,
+    max: u32,
+}
+
+impl<const BUCKETS: usize, const WIDTH: u32> Histogram<BUCKETS, WIDTH> {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            count: 0,
+            sum: 0,
+            min: u32::MAX,
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, value: u32) {
+        let index = ((value / WIDTH) as usize).min(BUCKETS - 1);
+        self.buckets[index] = self.buckets[index].saturating_add(1);
+
+        self.count = self.count.saturating_add(1);
+        self.sum = self.sum.saturating_add(value as u64);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Upper bound of the bucket containing `percent`% of values, clamped to maximum value.
+    pub fn percentile(&self, percent: u32) -> u32 {
+        if self.count == 0 {
+            return 0;
+        }
+
+        let target = (self.count as u64 * percent as u64).div_ceil(100);
+        let mut cumulative = 0_u64;
+        // Last bucket has no upper bound
+        for (i, count) in self.buckets[..BUCKETS - 1].iter().enumerate() {
+            cumulative += *count as u64;
+            if cumulative >= target {
+                return ((i as u32 + 1) * WIDTH).min(self.max);
+            }
+        }
+
+        self.max
+    }
+
+    pub fn summary(&self) -> HistogramSummary {
+        if self.count == 0 {
+            return HistogramSummary::default();
+        }
+
+        HistogramSummary {
+            count: self.count,
+            min: self.min,
+            avg: (self.sum / self.count as u64) as u32,
+            max: self.max,
+            p99: self.percentile(99),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<const BUCKETS: usize, const WIDTH: u32> Default for Histogram<BUCKETS, WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::histogram::{Histogram, HistogramSummary};
+
+    #[test]
+    fn empty_test() {
+        let histogram = Histogram::<8, 10>::new();
+        assert_eq!(histogram.summary(), HistogramSummary::default());
+    }
+
+    #[test]
+    fn summary_test() {
+        let mut histogram = Histogram::<16, 10>::new();
+        // 1..=100
+        for value in 1..=100 {
+            histogram.record(value);
+        }
+
+        assert_eq!(
+            histogram.summary(),
+            HistogramSummary {
+                count: 100,
+                min: 1,
+                avg: 50,
+                max: 100,
+                p99: 100,
+            }
+        );
+        // 50th value is in 50..60 bucket
+        assert_eq!(histogram.percentile(50), 60);
+    }
+
+    #[test]
+    fn outlier_test() {
+        let mut histogram = Histogram::<16, 10>::new();
+        for _ in 0..99 {
+            histogram.record(5);
+        }
+        // Overflows into the last bucket
+        histogram.record(1000);
+
+        let summary = histogram.summary();
+        assert_eq!(summary.max, 1000);
+        // Rounded up to bucket boundary
+        assert_eq!(summary.p99, 10);
+        assert_eq!(histogram.percentile(100), 1000);
+    }
+}
@@ -1,7 +1,19 @@
 #![no_std]
 
 mod button;
+mod chatter;
+mod combo;
+mod dedup;
+mod histogram;
 mod knob;
+mod tap_dance;
+mod tap_hold;
 
 pub use button::ButtonDebouncer;
+pub use chatter::{Bounce, ChatterMeter};
+pub use combo::{ComboOutput, ComboResolver};
+pub use dedup::ReportDedup;
+pub use histogram::{Histogram, HistogramSummary};
 pub use knob::{KnobFilter, KnobValue};
+pub use tap_dance::{TapDance, TapDanceAction};
+pub use tap_hold::{TapHold, TapHoldAction};
@@ -0,0 +1,210 @@
+/// Resolved action of a tap dance key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TapDanceAction {
+    /// Nothing pressed
+    None,
+    /// Tapped once
+    Tap,
+    /// Held on first press
+    Hold,
+    /// Tapped twice
+    DoubleTap,
+    /// Held on second press
+    TapHold,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// First press, undecided
+    FirstPress,
+    /// Released after first press, waiting for second press
+    FirstRelease,
+    /// Second press, undecided
+    SecondPress,
+    /// Held action pressed until release
+    Holding(TapDanceAction),
+    /// Tap action pressed for `tap_ms`
+    Tapping(TapDanceAction),
+}
+
+/// Tap dance resolver for a key with tap, hold, double tap and tap-hold actions.
+///
+/// Each press and release is decided within tapping term. A press held for tapping term
+/// holds [`TapDanceAction::Hold`] or [`TapDanceAction::TapHold`] until release.
+/// A release not followed by another press within tapping term is a single tap.
+/// A second release is a double tap at once. Taps are pressed for `tap_ms`.
+pub struct TapDance {
+    state: State,
+    /// Time in current state
+    elapsed_ms: u16,
+    /// Pressed while tapping, starts a new dance after the current tap
+    queued: bool,
+}
+
+impl TapDance {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Idle,
+            elapsed_ms: 0,
+            queued: false,
+        }
+    }
+
+    /// Undecided or tapping, time must keep advancing
+    pub fn pending(&self) -> bool {
+        matches!(
+            self.state,
+            State::FirstPress | State::FirstRelease | State::SecondPress | State::Tapping(_)
+        )
+    }
+
+    pub fn update(
+        &mut self,
+        pressed: bool,
+        elapsed_ms: u16,
+        tapping_term_ms: u16,
+        tap_ms: u16,
+    ) -> TapDanceAction {
+        self.elapsed_ms = self.elapsed_ms.saturating_add(elapsed_ms);
+        let term_passed = self.elapsed_ms >= tapping_term_ms;
+
+        match self.state {
+            State::Idle if pressed => self.enter(State::FirstPress),
+            State::Idle => {}
+
+            State::FirstPress if !pressed => self.enter(State::FirstRelease),
+            State::FirstPress if term_passed => self.enter(State::Holding(TapDanceAction::Hold)),
+            State::FirstPress => {}
+
+            State::FirstRelease if pressed => self.enter(State::SecondPress),
+            State::FirstRelease if term_passed => self.enter(State::Tapping(TapDanceAction::Tap)),
+            State::FirstRelease => {}
+
+            State::SecondPress if !pressed => self.enter(State::Tapping(TapDanceAction::DoubleTap)),
+            State::SecondPress if term_passed => {
+                self.enter(State::Holding(TapDanceAction::TapHold))
+            }
+            State::SecondPress => {}
+
+            State::Holding(_) if !pressed => self.enter(State::Idle),
+            State::Holding(_) => {}
+
+            // Finish the tap before handling another press.
+            // A press already released waits for a second press like a first release.
+            State::Tapping(_) => {
+                self.queued |= pressed;
+                if self.elapsed_ms >= tap_ms {
+                    self.enter(match (pressed, self.queued) {
+                        (true, _) => State::FirstPress,
+                        (false, true) => State::FirstRelease,
+                        (false, false) => State::Idle,
+                    })
+                }
+            }
+        }
+
+        match self.state {
+            State::Holding(action) | State::Tapping(action) => action,
+            _ => TapDanceAction::None,
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        self.state = state;
+        self.elapsed_ms = 0;
+        self.queued = false;
+    }
+}
+
+impl Default for TapDance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tap_dance::{TapDance, TapDanceAction};
+
+    const TERM: u16 = 3;
+    const TAP: u16 = 2;
+
+    fn run(seq: &[(bool, TapDanceAction)]) {
+        let mut key = TapDance::new();
+        for (i, (pressed, action)) in seq.iter().enumerate() {
+            assert_eq!(key.update(*pressed, 1, TERM, TAP), *action, "step {i}");
+        }
+        assert!(!key.pending());
+    }
+
+    #[test]
+    fn tap_test() {
+        run(&[
+            (true, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            // Tapping term passed without second press
+            (false, TapDanceAction::Tap),
+            (false, TapDanceAction::Tap),
+            (false, TapDanceAction::None),
+        ]);
+    }
+
+    #[test]
+    fn hold_test() {
+        run(&[
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::Hold),
+            (true, TapDanceAction::Hold),
+            (false, TapDanceAction::None),
+        ]);
+    }
+
+    #[test]
+    fn double_tap_test() {
+        run(&[
+            (true, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            // Second release decides at once
+            (false, TapDanceAction::DoubleTap),
+            (false, TapDanceAction::DoubleTap),
+            (false, TapDanceAction::None),
+        ]);
+    }
+
+    #[test]
+    fn tap_hold_test() {
+        run(&[
+            (true, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (true, TapDanceAction::TapHold),
+            (false, TapDanceAction::None),
+        ]);
+    }
+
+    #[test]
+    fn press_while_tapping_test() {
+        // Third press during double tap starts a new dance
+        run(&[
+            (true, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (true, TapDanceAction::None),
+            (false, TapDanceAction::DoubleTap),
+            (true, TapDanceAction::DoubleTap),
+            (false, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (false, TapDanceAction::None),
+            (false, TapDanceAction::Tap),
+            (false, TapDanceAction::Tap),
+            (false, TapDanceAction::None),
+        ]);
+    }
+}
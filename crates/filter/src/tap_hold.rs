@@ -0,0 +1,200 @@
+/// Resolved role of a dual-role key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TapHoldAction {
+    /// Nothing pressed
+    None,
+    /// Tap role pressed
+    Tap,
+    /// Hold role pressed
+    Hold,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Pressed, undecided
+    Pending,
+    Holding,
+    /// Released before tapping term, tap role pressed for `tap_ms`
+    Tapping,
+    /// Tap role released for `tap_ms` before tapping again
+    Releasing,
+}
+
+/// Tap/hold resolver for dual-role key.
+///
+/// A press held for tapping term is a hold.
+/// A release before it is a tap. Tap role is pressed for `tap_ms` after the release,
+/// so the host can see it.
+pub struct TapHold {
+    state: State,
+    /// Time in current state
+    elapsed_ms: u16,
+    /// Pressed while tapping, tapped again after the current tap
+    queued: bool,
+}
+
+impl TapHold {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Idle,
+            elapsed_ms: 0,
+            queued: false,
+        }
+    }
+
+    /// Undecided or tapping, time must keep advancing
+    pub fn pending(&self) -> bool {
+        matches!(
+            self.state,
+            State::Pending | State::Tapping | State::Releasing
+        )
+    }
+
+    pub fn update(
+        &mut self,
+        pressed: bool,
+        elapsed_ms: u16,
+        tapping_term_ms: u16,
+        tap_ms: u16,
+    ) -> TapHoldAction {
+        self.elapsed_ms = self.elapsed_ms.saturating_add(elapsed_ms);
+
+        match self.state {
+            State::Idle if pressed => self.enter(State::Pending),
+            State::Idle => {}
+
+            State::Pending if !pressed => self.enter(State::Tapping),
+            State::Pending if self.elapsed_ms >= tapping_term_ms => self.enter(State::Holding),
+            State::Pending => {}
+
+            State::Holding if !pressed => self.enter(State::Idle),
+            State::Holding => {}
+
+            // Finish the tap before handling another press.
+            // A press already released is tapped after releasing the tap role.
+            State::Tapping => {
+                self.queued |= pressed;
+                if self.elapsed_ms >= tap_ms {
+                    self.enter(match (pressed, self.queued) {
+                        (true, _) => State::Pending,
+                        (false, true) => State::Releasing,
+                        (false, false) => State::Idle,
+                    })
+                }
+            }
+
+            State::Releasing if self.elapsed_ms >= tap_ms => self.enter(State::Tapping),
+            State::Releasing => {}
+        }
+
+        match self.state {
+            State::Idle | State::Pending | State::Releasing => TapHoldAction::None,
+            State::Holding => TapHoldAction::Hold,
+            State::Tapping => TapHoldAction::Tap,
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        self.state = state;
+        self.elapsed_ms = 0;
+        self.queued = false;
+    }
+}
+
+impl Default for TapHold {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tap_hold::{TapHold, TapHoldAction};
+
+    const TERM: u16 = 5;
+    const TAP: u16 = 2;
+
+    fn run(seq: &[(bool, TapHoldAction)]) {
+        let mut key = TapHold::new();
+        for (i, (pressed, action)) in seq.iter().enumerate() {
+            assert_eq!(key.update(*pressed, 1, TERM, TAP), *action, "step {i}");
+        }
+    }
+
+    #[test]
+    fn tap_test() {
+        run(&[
+            (true, TapHoldAction::None),
+            (true, TapHoldAction::None),
+            (false, TapHoldAction::Tap),
+            (false, TapHoldAction::Tap),
+            (false, TapHoldAction::None),
+        ]);
+    }
+
+    #[test]
+    fn hold_test() {
+        run(&[
+            (true, TapHoldAction::None),
+            (true, TapHoldAction::None),
+            (true, TapHoldAction::None),
+            (true, TapHoldAction::None),
+            (true, TapHoldAction::None),
+            // Tapping term reached
+            (true, TapHoldAction::Hold),
+            (true, TapHoldAction::Hold),
+            (false, TapHoldAction::None),
+            (false, TapHoldAction::None),
+        ]);
+    }
+
+    #[test]
+    fn quick_release_test() {
+        // Released just before tapping term is a tap
+        let mut key = TapHold::new();
+        assert_eq!(key.update(true, 0, TERM, TAP), TapHoldAction::None);
+        assert_eq!(key.update(true, TERM - 1, TERM, TAP), TapHoldAction::None);
+        assert!(key.pending());
+        assert_eq!(key.update(false, 0, TERM, TAP), TapHoldAction::Tap);
+        assert_eq!(key.update(false, TAP, TERM, TAP), TapHoldAction::None);
+        assert!(!key.pending());
+
+        // Pressed again while tapping starts a new press after the tap
+        run(&[
+            (true, TapHoldAction::None),
+            (false, TapHoldAction::Tap),
+            (true, TapHoldAction::Tap),
+            (true, TapHoldAction::None),
+            (false, TapHoldAction::Tap),
+        ]);
+    }
+
+    #[test]
+    fn double_tap_test() {
+        // Second tap pressed and released while the first is tapping
+        run(&[
+            (true, TapHoldAction::None),
+            (false, TapHoldAction::Tap),
+            (true, TapHoldAction::Tap),
+            // Tap role released before the second tap
+            (false, TapHoldAction::None),
+            (false, TapHoldAction::None),
+            (false, TapHoldAction::Tap),
+            (false, TapHoldAction::Tap),
+            (false, TapHoldAction::None),
+        ]);
+    }
+
+    #[test]
+    fn tap_duration_test() {
+        // Tap lasts for the given duration regardless of update interval
+        let mut key = TapHold::new();
+        assert_eq!(key.update(true, 0, TERM, 20), TapHoldAction::None);
+        assert_eq!(key.update(false, 1, TERM, 20), TapHoldAction::Tap);
+        assert_eq!(key.update(false, 19, TERM, 20), TapHoldAction::Tap);
+        assert!(key.pending());
+        assert_eq!(key.update(false, 1, TERM, 20), TapHoldAction::None);
+        assert!(!key.pending());
+    }
+}
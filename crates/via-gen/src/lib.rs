@@ -0,0 +1,247 @@
+pub mod source;
+
+use core::error::Error;
+use std::{collections::BTreeSet, fs, path::Path};
+
+use serde_json::{Value, json};
+
+use crate::source::{CustomKeycode, ValueId};
+
+/// Firmware sources the definition is generated from, relative to firmware directory
+pub const SOURCES: &[&str] = &[
+    "via.json",
+    "src/keycodes.rs",
+    "src/usb/config.rs",
+    "src/via/custom.rs",
+    "src/via/keymap.rs",
+];
+
+/// First custom keycode. Via assigns custom keycodes from here in `customKeycodes` order
+const QK_KB_0: u16 = 0x7E00;
+
+/// Custom value channel of menu items
+const CUSTOM_CHANNEL: u64 = 0;
+
+/// Generate Via definition of firmware at `dir`.
+///
+/// `via.json` provides presentation (layout, menu labels).
+/// Device id, matrix, custom keycodes and menu value ids are replaced with ones from the sources,
+/// and the layout keys are checked against the keymap matrix.
+pub fn generate(dir: impl AsRef<Path>) -> Result<Value, Box<dyn Error>> {
+    let dir = dir.as_ref();
+    let read = |path: &str| {
+        fs::read_to_string(dir.join(path)).map_err(|err| format!("failed to read {path}: {err}"))
+    };
+
+    let mut def: Value = serde_json::from_str(&read("via.json")?)?;
+    let (vid, pid) = source::hid_device_id(&read("src/usb/config.rs")?)?;
+    let keycodes = source::custom_keycodes(&read("src/keycodes.rs")?)?;
+    let matrix = source::keymap_matrix(&read("src/via/keymap.rs")?)?;
+    let value_ids = source::value_ids(&read("src/via/custom.rs")?)?;
+
+    check_keycodes(&keycodes)?;
+    check_layout(&def, &matrix)?;
+    assign_menu_ids(&mut def, &value_ids)?;
+
+    let rows = matrix.iter().map(|(row, _)| row + 1).max().unwrap_or(0);
+    let cols = matrix.iter().map(|(_, col)| col + 1).max().unwrap_or(0);
+
+    def["vendorId"] = json!(format!("0x{vid:04X}"));
+    def["productId"] = json!(format!("0x{pid:04X}"));
+    def["matrix"] = json!({ "rows": rows, "cols": cols });
+    def["customKeycodes"] = keycodes
+        .iter()
+        .map(|keycode| {
+            json!({
+                "name": keycode.name,
+                "title": keycode.title,
+                "shortName": keycode.short_name,
+            })
+        })
+        .collect();
+
+    Ok(def)
+}
+
+/// Custom keycodes must be contiguous from [`QK_KB_0`]
+fn check_keycodes(keycodes: &[CustomKeycode]) -> Result<(), Box<dyn Error>> {
+    for (keycode, expected) in keycodes.iter().zip(QK_KB_0..) {
+        if keycode.keycode != expected {
+            return Err(format!(
+                "custom keycode {} is {:#06X}, but Via assigns {:#06X} by order",
+                keycode.short_name, keycode.keycode, expected
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Layout keys must match keymap matrix
+fn check_layout(def: &Value, matrix: &[(u8, u8)]) -> Result<(), Box<dyn Error>> {
+    let Some(rows) = def["layouts"]["keymap"].as_array() else {
+        return Err("via.json has no layouts.keymap".into());
+    };
+
+    let mut layout_keys = BTreeSet::new();
+    for legend in rows
+        .iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        // Top left legend is matrix position. Encoders have it empty.
+        let position = legend.split('\n').next().unwrap_or_default();
+        if position.is_empty() {
+            continue;
+        }
+
+        let key = position
+            .split_once(',')
+            .and_then(|(row, col)| Some((row.parse::<u8>().ok()?, col.parse::<u8>().ok()?)))
+            .ok_or_else(|| format!("invalid layout key {position:?}"))?;
+        layout_keys.insert(key);
+    }
+
+    let matrix_keys = matrix.iter().copied().collect::<BTreeSet<_>>();
+    if let Some((row, col)) = layout_keys.difference(&matrix_keys).next() {
+        return Err(format!("layout key {row},{col} is not in keymap matrix").into());
+    }
+    if let Some((row, col)) = matrix_keys.difference(&layout_keys).next() {
+        return Err(format!("keymap matrix key {row},{col} is missing in layout").into());
+    }
+
+    Ok(())
+}
+
+/// Assign value ids to menu items by name.
+///
+/// `id_<name>` item gets `ValueId::<NAME>`.
+/// Items of id range `ValueId::<NAME>_START..=<NAME>_END` are named `id_<name>_*` and get ids in order.
+/// Every value id must have exactly one menu item.
+fn assign_menu_ids(def: &mut Value, value_ids: &[ValueId]) -> Result<(), Box<dyn Error>> {
+    let mut used = BTreeSet::new();
+    for_each_menu_item(&mut def["menus"], &mut |name, channel, id| {
+        if *channel != CUSTOM_CHANNEL {
+            return Err(format!("menu item {name} is not on custom channel").into());
+        }
+
+        let Some(key) = name.strip_prefix("id_").map(str::to_uppercase) else {
+            return Err(format!("menu item {name} is not named id_<value>").into());
+        };
+
+        let assigned = if let Some(value_id) = value_ids
+            .iter()
+            .find(|value_id| value_id.name == key && value_id.ids.start() == value_id.ids.end())
+        {
+            *value_id.ids.start()
+        } else if let Some(value_id) = value_ids.iter().find(|value_id| {
+            value_id.ids.start() != value_id.ids.end()
+                && key.starts_with(&format!("{}_", value_id.name))
+        }) {
+            let Some(next) = value_id.ids.clone().find(|id| !used.contains(id)) else {
+                return Err(format!("too many menu items for ValueId::{}", value_id.name).into());
+            };
+            next
+        } else {
+            return Err(format!("menu item {name} has no matching ValueId").into());
+        };
+
+        if !used.insert(assigned) {
+            return Err(format!("menu item {name} reuses value id {assigned:#04X}").into());
+        }
+        *id = assigned.into();
+        Ok(())
+    })?;
+
+    for value_id in value_ids {
+        if let Some(id) = value_id.ids.clone().find(|id| !used.contains(id)) {
+            return Err(format!("ValueId::{} {id:#04X} has no menu item", value_id.name).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Call `f` with name, channel and value id of each menu item
+fn for_each_menu_item(
+    value: &mut Value,
+    f: &mut impl FnMut(&str, &u64, &mut u64) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    match value {
+        Value::Array(array) => {
+            for value in array {
+                for_each_menu_item(value, f)?;
+            }
+        }
+
+        Value::Object(object) => {
+            let Some(content) = object.get_mut("content") else {
+                return Ok(());
+            };
+
+            if let Some(
+                [
+                    Value::String(name),
+                    Value::Number(channel),
+                    Value::Number(id),
+                ],
+            ) = content.as_array_mut().map(Vec::as_mut_slice)
+                && let (Some(channel), Some(mut value_id)) = (channel.as_u64(), id.as_u64())
+            {
+                f(name, &channel, &mut value_id)?;
+                *id = value_id.into();
+            } else {
+                for_each_menu_item(content, f)?;
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::Value;
+
+    use crate::source::{self, CustomKeycode};
+
+    const FIRMWARE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../firmware");
+
+    #[test]
+    fn custom_keycodes_test() {
+        let src = r#"
+            /// Gamepad Button 1 (Joystick Btn1)
+            pub const JOY_BTN1: Keycode = Keycode(0x7E00);
+            pub const NOT_KEYCODE: u16 = 0;
+        "#;
+
+        assert_eq!(
+            source::custom_keycodes(src).unwrap(),
+            [CustomKeycode {
+                short_name: "JOY_BTN1".into(),
+                keycode: 0x7E00,
+                name: "Joystick Btn1".into(),
+                title: "Gamepad Button 1".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn via_json_up_to_date_test() {
+        let generated = crate::generate(FIRMWARE_DIR).unwrap();
+        let file: Value =
+            serde_json::from_str(&fs::read_to_string(format!("{FIRMWARE_DIR}/via.json")).unwrap())
+                .unwrap();
+
+        assert!(
+            generated == file,
+            "firmware/via.json is outdated. Run `cargo xtask via-json --write`"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Definitions parsed from firmware sources
+
+use core::{error::Error, ops::RangeInclusive};
+
+use syn::{Attribute, Expr, ExprLit, ImplItem, Item, Lit, Pat, Stmt, Type};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomKeycode {
+    pub short_name: String,
+    pub keycode: u16,
+    /// Label shown on key
+    pub name: String,
+    /// Tooltip
+    pub title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueId {
+    pub name: String,
+    /// Single id, or id range of consts named `<name>_START` and `<name>_END`
+    pub ids: RangeInclusive<u8>,
+}
+
+/// Parse custom keycode consts of `keycodes.rs`.
+/// Docs are in `Title (Name)` form.
+pub fn custom_keycodes(src: &str) -> Result<Vec<CustomKeycode>, Box<dyn Error>> {
+    let file = syn::parse_file(src)?;
+
+    let mut keycodes = vec![];
+    for item in file.items {
+        let Item::Const(item) = item else {
+            continue;
+        };
+        if !type_is(&item.ty, "Keycode") {
+            continue;
+        }
+
+        let short_name = item.ident.to_string();
+        let Expr::Call(call) = &*item.expr else {
+            return Err(format!("{short_name} is not defined as `Keycode(<value>)`").into());
+        };
+        let keycode = call
+            .args
+            .first()
+            .and_then(lit_int::<u16>)
+            .ok_or_else(|| format!("{short_name} keycode is not an integer literal"))?;
+
+        let doc = doc(&item.attrs);
+        let Some((title, name)) = doc.strip_suffix(')').and_then(|doc| doc.rsplit_once(" ("))
+        else {
+            return Err(format!("{short_name} doc is not in `Title (Name)` form").into());
+        };
+
+        keycodes.push(CustomKeycode {
+            name: name.to_string(),
+            title: title.to_string(),
+            short_name,
+            keycode,
+        });
+    }
+
+    Ok(keycodes)
+}
+
+/// Parse custom channel value ids from `ValueId` impl of `via/custom.rs`
+pub fn value_ids(src: &str) -> Result<Vec<ValueId>, Box<dyn Error>> {
+    let file = syn::parse_file(src)?;
+
+    let Some(items) = file.items.into_iter().find_map(|item| match item {
+        Item::Impl(item) if item.trait_.is_none() && type_is(&item.self_ty, "ValueId") => {
+            Some(item.items)
+        }
+        _ => None,
+    }) else {
+        return Err("`impl ValueId` not found".into());
+    };
+
+    let mut consts = vec![];
+    for item in items {
+        let ImplItem::Const(item) = item else {
+            continue;
+        };
+
+        let name = item.ident.to_string();
+        let id = lit_int::<u8>(&item.expr)
+            .ok_or_else(|| format!("ValueId::{name} is not an integer literal"))?;
+        consts.push((name, id));
+    }
+
+    let mut value_ids = vec![];
+    for (name, id) in &consts {
+        if name.ends_with("_END") {
+            continue;
+        }
+
+        let Some(base) = name.strip_suffix("_START") else {
+            value_ids.push(ValueId {
+                name: name.clone(),
+                ids: *id..=*id,
+            });
+            continue;
+        };
+
+        let end_name = format!("{base}_END");
+        let Some((_, end)) = consts.iter().find(|(name, _)| *name == end_name) else {
+            return Err(format!("ValueId::{name} has no matching {end_name}").into());
+        };
+        value_ids.push(ValueId {
+            name: base.to_string(),
+            ids: *id..=*end,
+        });
+    }
+
+    Ok(value_ids)
+}
+
+/// Parse `(row, col)` match arms of `get_keymap_keycode` in `via/keymap.rs`
+pub fn keymap_matrix(src: &str) -> Result<Vec<(u8, u8)>, Box<dyn Error>> {
+    let file = syn::parse_file(src)?;
+
+    let Some(block) = file.items.into_iter().find_map(|item| match item {
+        Item::Fn(item) if item.sig.ident == "get_keymap_keycode" => Some(item.block),
+        _ => None,
+    }) else {
+        return Err("fn get_keymap_keycode not found".into());
+    };
+
+    let Some(Stmt::Expr(Expr::Match(expr), _)) = block.stmts.last() else {
+        return Err("get_keymap_keycode does not end with a match expression".into());
+    };
+
+    let mut keys = vec![];
+    for arm in &expr.arms {
+        let Pat::Tuple(tuple) = &arm.pat else {
+            continue;
+        };
+
+        let mut elems = tuple.elems.iter().map(|pat| match pat {
+            Pat::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }) => lit.base10_parse::<u8>().ok(),
+            _ => None,
+        });
+        match (elems.next(), elems.next(), elems.next()) {
+            (Some(Some(row)), Some(Some(col)), None) => keys.push((row, col)),
+            _ => return Err("keymap match arm is not a (row, col) literal".into()),
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Parse vendor and product id of `hid_device_config` in `usb/config.rs`
+pub fn hid_device_id(src: &str) -> Result<(u16, u16), Box<dyn Error>> {
+    let file = syn::parse_file(src)?;
+
+    let Some(block) = file.items.into_iter().find_map(|item| match item {
+        Item::Fn(item) if item.sig.ident == "hid_device_config" => Some(item.block),
+        _ => None,
+    }) else {
+        return Err("fn hid_device_config not found".into());
+    };
+
+    for stmt in &block.stmts {
+        let Stmt::Local(local) = stmt else {
+            continue;
+        };
+        let Some(Expr::Call(call)) = local.init.as_ref().map(|init| &*init.expr) else {
+            continue;
+        };
+
+        let mut args = call.args.iter().map(lit_int::<u16>);
+        if let (Some(Some(vid)), Some(Some(pid)), None) = (args.next(), args.next(), args.next()) {
+            return Ok((vid, pid));
+        }
+    }
+
+    Err("usb device id not found in hid_device_config".into())
+}
+
+fn type_is(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident(name))
+}
+
+fn lit_int<N>(expr: &Expr) -> Option<N>
+where
+    N: core::str::FromStr,
+    N::Err: core::fmt::Display,
+{
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+/// Joined doc comment lines
+fn doc(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }) => Some(lit.value().trim().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
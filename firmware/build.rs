@@ -1,35 +1,76 @@
-//! This build script copies the `memory.x` file from the crate root into
-//! a directory where the linker can always find it at build time.
-//! For many projects this is optional, as the linker always searches the
-//! project root directory -- wherever `Cargo.toml` is. However, if you
-//! are using a workspace or have a more complicated build setup, this
-//! build script becomes required. Additionally, by requesting that
-//! Cargo re-run the build script whenever `memory.x` is changed,
-//! updating `memory.x` ensures a rebuild of the application with the
+//! This build script copies the `memory-<chip>.x` file of the selected chip
+//! from the crate root into a directory where the linker can always find it
+//! as `memory.x` at build time. Additionally, by requesting that
+//! Cargo re-run the build script whenever memory files are changed,
+//! updating them ensures a rebuild of the application with the
 //! new memory settings.
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
 
+use lzma_rust2::{LzmaOptions, LzmaWriter};
+
 fn main() {
-    // Put `memory.x` in our output directory and ensure it's
+    // Put `memory-<chip>.x` in our output directory as `memory.x` and ensure it's
     // on the linker search path.
+    // Crate root must not have `memory.x`, as the linker would find it first.
+    let memory: &[u8] = if env::var_os("CARGO_FEATURE_RP2040").is_some() {
+        include_bytes!("memory-rp2040.x")
+    } else {
+        include_bytes!("memory-rp235x.x")
+    };
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory)
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
     // By default, Cargo will re-run a build script whenever
-    // any file in the project changes. By specifying `memory.x`
+    // any file in the project changes. By specifying memory files
     // here, we ensure the build script is only re-run when
-    // `memory.x` is changed.
-    println!("cargo:rerun-if-changed=memory.x");
+    // they are changed.
+    println!("cargo:rerun-if-changed=memory-rp235x.x");
+    println!("cargo:rerun-if-changed=memory-rp2040.x");
+
+    // Embed keyboard definition generated from sources, served to Via and Vial from the device.
+    // Fails the build if via.json disagrees with the sources.
+    let definition = via_gen::generate(".")
+        .unwrap_or_else(|err| panic!("Via definition does not match firmware: {err}"));
+    File::create(out.join("via_definition.lzma"))
+        .unwrap()
+        .write_all(&compress_lzma(definition.to_string().as_bytes()))
+        .unwrap();
+    for source in via_gen::SOURCES {
+        println!("cargo:rerun-if-changed={source}");
+    }
+
+    // Pinout macros of the board selected with `BOARD`.
+    // Fails the build on invalid pins or pin conflicts.
+    let board = env::var("BOARD").unwrap_or_else(|_| board_gen::DEFAULT_BOARD.to_string());
+    let board_path = format!("boards/{board}.toml");
+    let board_src = fs::read_to_string(&board_path)
+        .unwrap_or_else(|err| panic!("failed to read board definition {board_path}: {err}"));
+    let pinout = board_gen::generate(&board_src)
+        .unwrap_or_else(|err| panic!("invalid board definition {board_path}: {err}"));
+    fs::write(out.join("board.rs"), pinout).unwrap();
+    println!("cargo:rerun-if-changed={board_path}");
+    println!("cargo:rerun-if-env-changed=BOARD");
 
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
     println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
 }
+
+/// Compress into `.lzma` (LZMA alone) format, which Vial decompresses
+fn compress_lzma(data: &[u8]) -> Vec<u8> {
+    let mut options = LzmaOptions::with_preset(9);
+    // Definition is a few KiB, keep the dictionary small for decoders
+    options.dict_size = 1 << 16;
+    let mut writer = LzmaWriter::new_use_header(Vec::new(), &options, Some(data.len() as u64))
+        .expect("invalid LZMA options");
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap()
+}
@@ -0,0 +1,89 @@
+__userdata_size = 4k;
+__usage_size = 4k;
+
+MEMORY {
+    /*
+     * Second stage bootloader, loaded by the Boot ROM from the first 256 bytes of flash
+     */
+    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
+    /*
+     * 2 MiB flash of a Pico. Must match `chip::FLASH_SIZE`
+     */
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - __userdata_size - __usage_size
+    /*
+     * Usage statistics area, written as an append-only log of records
+     */
+    USAGE(rw) : ORIGIN = 0x10000000 + (2048k - __userdata_size - __usage_size), LENGTH = __usage_size
+    /*
+     * Userdata area for persistent firmware data storage
+     */
+    USERDATA(rw) : ORIGIN = 0x10000000 + (2048k - __userdata_size), LENGTH = __userdata_size
+    /*
+     * SRAM0-SRAM3 with a striped mapping. SRAM4 and SRAM5 (4K each) are unused.
+     */
+    RAM : ORIGIN = 0x20000000, LENGTH = 256K
+}
+
+SECTIONS {
+    /* ### Boot loader
+     *
+     * Selected with `boot2-*` feature of embassy-rp
+     */
+    .boot2 ORIGIN(BOOT2) :
+    {
+        KEEP(*(.boot2));
+    } > BOOT2
+} INSERT BEFORE .text;
+
+SECTIONS {
+    /* ### Picotool 'Binary Info' Header Block
+     *
+     * Picotool only searches the second 256 bytes of flash, so it goes
+     * right after .vector_table
+     */
+    .boot_info : ALIGN(4)
+    {
+        KEEP(*(.boot_info));
+    } > FLASH
+
+} INSERT AFTER .vector_table;
+
+/* move .text to start /after/ the boot info */
+_stext = ADDR(.boot_info) + SIZEOF(.boot_info);
+
+SECTIONS {
+    /* ### Picotool 'Binary Info' Entries
+     *
+     * Picotool looks through this block (as we have pointers to it in our
+     * header) to find interesting information.
+     */
+    .bi_entries : ALIGN(4)
+    {
+        /* We put this in the header */
+        __bi_entries_start = .;
+        /* Here are the entries */
+        KEEP(*(.bi_entries));
+        /* Keep this block a nice round size */
+        . = ALIGN(4);
+        /* We put this in the header */
+        __bi_entries_end = .;
+    } > FLASH
+} INSERT AFTER .text;
+
+SECTIONS {
+    /*
+     * Userdata info
+     */
+    .userdata :
+    {
+        __userdata_start = .;
+    } > USERDATA
+
+    /*
+     * Usage statistics info
+     */
+    .usage :
+    {
+        __usage_start = .;
+    } > USAGE
+}
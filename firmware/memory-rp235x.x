@@ -1,12 +1,18 @@
 __userdata_size = 4k;
+__usage_size = 4k;
 
 MEMORY {
     /*
      * The RP2350 has either external or internal flash.
      *
      * 2 MiB is a safe default here, although a Pico 2 has 4 MiB.
+     * Must match `chip::FLASH_SIZE`.
      */
-    FLASH : ORIGIN = 0x10000000, LENGTH = 2048K - __userdata_size
+    FLASH : ORIGIN = 0x10000000, LENGTH = 2048K - __userdata_size - __usage_size
+    /*
+     * Usage statistics area, written as an append-only log of records
+     */
+    USAGE(rw) : ORIGIN = 0x10000000 + (2048k - __userdata_size - __usage_size), LENGTH = __usage_size
     /*
      * Userdata area for persistent firmware data storage
      */
@@ -88,4 +94,12 @@ SECTIONS {
     {
         __userdata_start = .;
     } > USERDATA
+
+    /*
+     * Usage statistics info
+     */
+    .usage :
+    {
+        __usage_start = .;
+    } > USAGE
 }
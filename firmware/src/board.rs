@@ -0,0 +1,4 @@
+//! Pinout of the board selected with `BOARD` at build time, generated from `boards/<BOARD>.toml`.
+//! Provides `input_pinout!(p)` and `led_pinout!(p)` taking fields of [`Peripherals`](embassy_rp::Peripherals) `p`.
+
+include!(concat!(env!("OUT_DIR"), "/board.rs"));
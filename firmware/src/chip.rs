@@ -0,0 +1,36 @@
+//! Chip specific parts, selected with `rp235xa` or `rp2040` feature.
+
+#[cfg(all(feature = "rp235xa", feature = "rp2040"))]
+compile_error!(
+    "Features `rp235xa` and `rp2040` are exclusive. Build RP2040 with `--no-default-features`"
+);
+
+#[cfg(not(any(feature = "rp235xa", feature = "rp2040")))]
+compile_error!("Select chip with `rp235xa` or `rp2040` feature");
+
+use embassy_rp::rom_data;
+
+/// Flash size, matching `memory.x` of the chip
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// PSM blocks reset by the watchdog: everything except oscillators
+#[cfg(feature = "rp235xa")]
+pub const WATCHDOG_RESET_SELECT: u32 = 0x01ff_ffff & !(0b11 << 2);
+/// PSM blocks reset by the watchdog: everything except oscillators
+#[cfg(feature = "rp2040")]
+pub const WATCHDOG_RESET_SELECT: u32 = 0x0001_ffff & !0b11;
+
+/// Reboot the chip
+pub fn reboot() {
+    #[cfg(feature = "rp235xa")]
+    rom_data::reboot(0, 1, 0, 0);
+
+    // RP2040 boot ROM has no reboot function
+    #[cfg(feature = "rp2040")]
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+/// Reboot to BOOTSEL mode for flashing
+pub fn reboot_to_bootsel() {
+    rom_data::reset_to_usb_boot(0, 0);
+}
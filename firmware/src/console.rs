@@ -0,0 +1,171 @@
+mod cmds;
+
+use core::fmt::{self, Write};
+
+use embassy_executor::SpawnToken;
+use embassy_usb::{
+    class::cdc_acm::{CdcAcmClass, Receiver, Sender, State},
+    driver::EndpointError,
+};
+use heapless::{String, Vec};
+use static_cell::StaticCell;
+
+use crate::{
+    log,
+    usb::{self, Driver},
+};
+
+/// Maximum length of a command line
+const LINE_LEN: usize = 64;
+
+/// Size of output buffer
+const OUT_LEN: usize = 1024;
+
+/// Longest line printed with [`Console::stream_line`]
+const STREAM_LINE_LEN: usize = 96;
+
+/// Replaces the end of output that did not fit in the buffer
+const TRUNCATED: &str = "\r\n(output truncated)\r\n";
+
+const _: () = assert!(STREAM_LINE_LEN + TRUNCATED.len() < OUT_LEN);
+
+/// Line based text console on a CDC ACM port
+pub fn console_task(
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(sender: Sender<'static, Driver>, receiver: Receiver<'static, Driver>) {
+        let mut console = Console {
+            sender,
+            receiver,
+            out: String::new(),
+            truncated: false,
+        };
+
+        loop {
+            console.receiver.wait_connection().await;
+            if let Err(e) = console.run().await {
+                log::debug!("Console disconnected. err: {:?}", e);
+            }
+        }
+    }
+
+    let (sender, receiver) = CdcAcmClass::new(
+        builder,
+        {
+            static STATE: StaticCell<State> = StaticCell::new();
+            STATE.init(State::new())
+        },
+        usb::config::DEVICE.max_packet_size_0 as u16,
+    )
+    .split();
+    inner(sender, receiver)
+}
+
+struct Console {
+    sender: Sender<'static, Driver>,
+    receiver: Receiver<'static, Driver>,
+
+    /// Pending output, sent on [`Console::flush`]
+    out: String<OUT_LEN>,
+    /// Output did not fit in [`Console::out`]
+    truncated: bool,
+}
+
+impl Console {
+    async fn run(&mut self) -> Result<(), EndpointError> {
+        let mut line = Vec::<u8, LINE_LEN>::new();
+        let mut last = 0_u8;
+        let mut buf = [0_u8; 64];
+
+        self.prompt().await?;
+        loop {
+            let len = self.receiver.read_packet(&mut buf).await?;
+            for &c in &buf[..len] {
+                match c {
+                    // Skip LF of CRLF
+                    b'\n' if last == b'\r' => {}
+
+                    b'\r' | b'\n' => {
+                        self.print("\r\n");
+                        // Line only contains printable ascii
+                        let cmd = core::str::from_utf8(&line).unwrap_or_default();
+                        self.execute(cmd).await?;
+                        line.clear();
+                        self.prompt().await?;
+                    }
+
+                    // Backspace, Delete
+                    0x08 | 0x7F if !line.is_empty() => {
+                        line.pop();
+                        self.print("\x08 \x08");
+                    }
+
+                    0x20..=0x7E if !line.is_full() => {
+                        _ = line.push(c);
+                        self.truncated |= self.out.push(c as char).is_err();
+                    }
+
+                    _ => {}
+                }
+                last = c;
+            }
+
+            self.flush().await?;
+        }
+    }
+
+    async fn prompt(&mut self) -> Result<(), EndpointError> {
+        self.print("> ");
+        self.flush().await
+    }
+
+    /// Append text to output
+    fn print(&mut self, s: &str) {
+        self.truncated |= self.out.push_str(s).is_err();
+    }
+
+    /// Append formatted line to output
+    fn println(&mut self, args: fmt::Arguments) {
+        self.truncated |= self.out.write_fmt(args).is_err();
+        self.print("\r\n");
+    }
+
+    /// Append formatted line to output, sending pending output first if the line may not fit.
+    /// Used for dumps longer than the output buffer.
+    async fn stream_line(&mut self, args: fmt::Arguments<'_>) -> Result<(), EndpointError> {
+        if OUT_LEN - self.out.len() < STREAM_LINE_LEN {
+            self.flush().await?;
+        }
+        self.println(args);
+        Ok(())
+    }
+
+    /// Send pending output
+    async fn flush(&mut self) -> Result<(), EndpointError> {
+        if self.truncated {
+            self.truncated = false;
+            let mut len = OUT_LEN - TRUNCATED.len();
+            while !self.out.is_char_boundary(len) {
+                len -= 1;
+            }
+            self.out.truncate(len);
+            self.print(TRUNCATED);
+        }
+
+        let max_packet_size = self.sender.max_packet_size() as usize;
+        let mut last_len = 0;
+        for chunk in self.out.as_bytes().chunks(max_packet_size) {
+            self.sender.write_packet(chunk).await?;
+            last_len = chunk.len();
+        }
+
+        // Terminate transfer ending with full packet
+        if last_len == max_packet_size {
+            self.sender.write_packet(&[]).await?;
+        }
+
+        self.out.clear();
+        Ok(())
+    }
+}
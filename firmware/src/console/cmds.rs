@@ -0,0 +1,583 @@
+use core::fmt;
+
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Timer};
+use embassy_usb::driver::EndpointError;
+use keycode::Keycode;
+
+use crate::{
+    chip,
+    console::{Console, OUT_LEN},
+    crash,
+    input::{
+        CURRENT_INPUT, chatter,
+        latency::{self, LatencyStage},
+        reader::button::{AUX_BUTTON_COUNT, ButtonId},
+        report::REPORT_STATS,
+    },
+    led, log,
+    userdata::{
+        self, LogFormat, UsbMode,
+        keymap::Keymap,
+        led::LedEffect,
+        midi::MidiKnobMode,
+        poll::PollIntervals,
+        stick::StickAxis,
+        usage::{self, UsageStats},
+    },
+};
+
+/// Interval of `input watch` updates
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+const HELP: &str = "\
+help                     Show this help\r
+input [watch]            Print input state. `watch` prints until a key is pressed\r
+userdata                 Print userdata\r
+stats [reset]            Print or reset report counters\r
+latency [on|off|reset]   Print latency in us, start, stop or reset measurement\r
+crash [clear]            Print or clear last crash reason\r
+usage [reset]            Print or reset button press counts and knob turns\r
+chatter [reset]          Print or reset switch bounce statistics\r
+set <name> <value>       Edit userdata. Run `userdata` for names\r
+save                     Save userdata to flash\r
+mode <mode>              Change USB mode and reboot (hid, eac, xinput, switch, midi)\r
+diagnostics <on|off>     Start or stop LED diagnostics\r
+reboot                   Reboot\r
+bootsel                  Reboot to BOOTSEL\r
+";
+
+// Help is printed at once, so it must fit in the output buffer
+const _: () = assert!(HELP.len() <= OUT_LEN);
+
+const MODES: [(&str, UsbMode); 5] = [
+    ("hid", UsbMode::Hid),
+    ("eac", UsbMode::Eac),
+    ("xinput", UsbMode::XInput),
+    ("switch", UsbMode::Switch),
+    ("midi", UsbMode::Midi),
+];
+
+const LED_EFFECTS: [(&str, LedEffect); 2] = [
+    ("static", LedEffect::Static),
+    ("breathing", LedEffect::Breathing),
+];
+
+const STICK_AXES: [(&str, StickAxis); 4] = [
+    ("left_x", StickAxis::LeftX),
+    ("left_y", StickAxis::LeftY),
+    ("right_x", StickAxis::RightX),
+    ("right_y", StickAxis::RightY),
+];
+
+const LOG_FORMATS: [(&str, LogFormat); 2] =
+    [("defmt", LogFormat::Defmt), ("text", LogFormat::Text)];
+
+const MIDI_KNOB_MODES: [(&str, MidiKnobMode); 2] = [
+    ("absolute", MidiKnobMode::Absolute),
+    ("relative", MidiKnobMode::Relative),
+];
+
+/// Buttons in [`MidiSettings::notes`](crate::userdata::midi::MidiSettings::notes) order
+const BUTTONS: [&str; 7] = [
+    "button1", "button2", "button3", "button4", "fx1", "fx2", "start",
+];
+
+/// Auxiliary buttons following [`BUTTONS`] in [`ButtonId`] order
+const AUX_BUTTONS: [&str; AUX_BUTTON_COUNT] = ["aux1", "aux2", "aux3", "aux4"];
+
+type KeymapField = fn(&mut Keymap) -> &mut Keycode;
+
+const KEYMAP: [(&str, KeymapField); 15] = [
+    ("button1", |map| &mut map.button1),
+    ("button2", |map| &mut map.button2),
+    ("button3", |map| &mut map.button3),
+    ("button4", |map| &mut map.button4),
+    ("fx1", |map| &mut map.fx1),
+    ("fx2", |map| &mut map.fx2),
+    ("start", |map| &mut map.start),
+    ("aux1", |map| &mut map.aux1),
+    ("aux2", |map| &mut map.aux2),
+    ("aux3", |map| &mut map.aux3),
+    ("aux4", |map| &mut map.aux4),
+    ("left_knob_left", |map| &mut map.left_knob_left),
+    ("left_knob_right", |map| &mut map.left_knob_right),
+    ("right_knob_left", |map| &mut map.right_knob_left),
+    ("right_knob_right", |map| &mut map.right_knob_right),
+];
+
+impl Console {
+    pub(super) async fn execute(&mut self, line: &str) -> Result<(), EndpointError> {
+        let mut args = line.split_ascii_whitespace();
+        let Some(cmd) = args.next() else {
+            return Ok(());
+        };
+
+        match (cmd, args.next(), args.next()) {
+            ("help", None, None) => self.print(HELP),
+
+            ("input", None, None) => self.print_input(),
+            ("input", Some("watch"), None) => self.watch_input().await?,
+
+            ("userdata", None, None) => self.print_userdata().await?,
+
+            ("stats", None, None) => self.print_stats(),
+            ("stats", Some("reset"), None) => {
+                for (_, stats) in &REPORT_STATS {
+                    stats.reset();
+                }
+                self.print("Report counters reset.\r\n");
+            }
+
+            ("set", Some(name), Some(value)) => {
+                if self.set(name, value).is_none() {
+                    self.println(format_args!("Invalid value `{}` for `{}`", value, name));
+                }
+            }
+
+            ("save", None, None) => {
+                userdata::save();
+                self.print("Saving userdata.\r\n");
+            }
+
+            ("latency", None, None) => self.print_latency(),
+            ("latency", Some("on"), None) => {
+                latency::set_enabled(true);
+                self.print("Latency measurement started.\r\n");
+            }
+            ("latency", Some("off"), None) => {
+                latency::set_enabled(false);
+                self.print("Latency measurement stopped.\r\n");
+            }
+            ("latency", Some("reset"), None) => {
+                latency::reset();
+                self.print("Latency measurement reset.\r\n");
+            }
+
+            ("crash", None, None) => match crash::last() {
+                Some(text) => self.println(format_args!("Last crash: {}", text.as_str())),
+                None => self.print("No crash recorded.\r\n"),
+            },
+            ("crash", Some("clear"), None) => {
+                crash::clear();
+                self.print("Crash record cleared.\r\n");
+            }
+
+            ("usage", None, None) => self.print_usage(),
+            ("usage", Some("reset"), None) => {
+                usage::reset();
+                userdata::save_usage();
+                self.print("Usage statistics reset.\r\n");
+            }
+
+            ("chatter", None, None) => self.print_chatter(),
+            ("chatter", Some("reset"), None) => {
+                chatter::reset();
+                self.print("Chatter statistics reset.\r\n");
+            }
+
+            ("mode", Some(name), None) => {
+                let Some(mode) = find(&MODES, name) else {
+                    self.println(format_args!("Unknown mode `{}`", name));
+                    return Ok(());
+                };
+
+                self.println(format_args!("Switching to {} mode and rebooting...", name));
+                self.flush().await?;
+
+                log::info!("{:?} mode enabled via console.", mode);
+                userdata::update(|data| {
+                    data.mode = mode;
+                });
+                userdata::save_and_reboot();
+            }
+
+            ("diagnostics", Some("on"), None) => {
+                log::info!("LED diagnostics started.");
+                led::start_diagnostics();
+            }
+
+            ("diagnostics", Some("off"), None) => {
+                log::info!("LED diagnostics stopped.");
+                led::stop_diagnostics();
+            }
+
+            ("reboot", None, None) => {
+                self.print("Rebooting...\r\n");
+                self.flush().await?;
+                chip::reboot();
+            }
+
+            ("bootsel", None, None) => {
+                self.print("Rebooting to BOOTSEL...\r\n");
+                self.flush().await?;
+                chip::reboot_to_bootsel();
+            }
+
+            _ => {
+                self.println(format_args!("Invalid command `{}`. Run `help`", line));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_input(&mut self) {
+        let input = CURRENT_INPUT.borrow().get();
+        let buttons = input.buttons;
+        let level = |button| buttons.level(button) as u8;
+
+        self.println(format_args!(
+            "BT-A:{} BT-B:{} BT-C:{} BT-D:{} FX-L:{} FX-R:{} Start:{} Aux:{}{}{}{} Left:{:4}({:+5}) Right:{:4}({:+5})",
+            level(ButtonId::BUTTON1),
+            level(ButtonId::BUTTON2),
+            level(ButtonId::BUTTON3),
+            level(ButtonId::BUTTON4),
+            level(ButtonId::FX1),
+            level(ButtonId::FX2),
+            level(ButtonId::START),
+            level(ButtonId::AUX1),
+            level(ButtonId::AUX2),
+            level(ButtonId::AUX3),
+            level(ButtonId::AUX4),
+            input.knobs.0.absolute,
+            input.knobs.0.delta,
+            input.knobs.1.absolute,
+            input.knobs.1.delta,
+        ));
+    }
+
+    async fn watch_input(&mut self) -> Result<(), EndpointError> {
+        let mut buf = [0_u8; 64];
+        loop {
+            self.print_input();
+            self.flush().await?;
+
+            match select(
+                Timer::after(WATCH_INTERVAL),
+                self.receiver.read_packet(&mut buf),
+            )
+            .await
+            {
+                Either::First(()) => {}
+                Either::Second(res) => {
+                    res?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Streamed, since the dump is longer than the output buffer
+    async fn print_userdata(&mut self) -> Result<(), EndpointError> {
+        let data = userdata::get(|data| data.clone());
+
+        self.stream_line(format_args!("mode = {}", name(&MODES, data.mode)))
+            .await?;
+        self.stream_line(format_args!(
+            "log.format = {}",
+            name(&LOG_FORMATS, data.log_format)
+        ))
+        .await?;
+        self.stream_line(format_args!("led.brightness = {}", data.led.brightness))
+            .await?;
+        self.stream_line(format_args!(
+            "led.effect = {}",
+            name(&LED_EFFECTS, data.led.effect)
+        ))
+        .await?;
+        self.stream_line(format_args!(
+            "knob.left = {}",
+            name(&STICK_AXES, data.knob_axes.left_knob)
+        ))
+        .await?;
+        self.stream_line(format_args!(
+            "knob.right = {}",
+            name(&STICK_AXES, data.knob_axes.right_knob)
+        ))
+        .await?;
+        self.stream_line(format_args!("midi.channel = {}", data.midi.channel + 1))
+            .await?;
+        self.stream_line(format_args!(
+            "midi.knob_mode = {}",
+            name(&MIDI_KNOB_MODES, data.midi.knob_mode)
+        ))
+        .await?;
+        self.stream_line(format_args!("midi.left_cc = {}", data.midi.left_knob_cc))
+            .await?;
+        self.stream_line(format_args!("midi.right_cc = {}", data.midi.right_knob_cc))
+            .await?;
+        for (button, note) in BUTTONS.iter().zip(data.midi.notes) {
+            self.stream_line(format_args!("midi.note.{} = {}", button, note))
+                .await?;
+        }
+
+        let mut poll = data.poll;
+        for (interface, field) in PollIntervals::FIELDS {
+            self.stream_line(format_args!("poll.{} = {}", interface, field(&mut poll)))
+                .await?;
+        }
+
+        self.stream_line(format_args!("tapping_term = {}", data.tapping_term_ms))
+            .await?;
+        self.stream_line(format_args!("combo_term = {}", data.combo_term_ms))
+            .await?;
+        for (mode_name, mode) in MODES {
+            self.stream_line(format_args!(
+                "hotkey.{} = {}",
+                mode_name,
+                ButtonNames(data.mode_hotkeys[mode as usize])
+            ))
+            .await?;
+        }
+
+        // Base layer only. Upper layers are edited with Via
+        let mut keymap = data.keymaps[0].clone();
+        for (key, field) in KEYMAP {
+            self.stream_line(format_args!(
+                "keymap.{} = {:#06x}",
+                key,
+                field(&mut keymap).0
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn print_stats(&mut self) {
+        self.println(format_args!(
+            "{:<10}{:>12}{:>12}{:>12}",
+            "interface", "sent", "dropped", "errors"
+        ));
+        for (interface, stats) in &REPORT_STATS {
+            let counts = stats.counts();
+            self.println(format_args!(
+                "{:<10}{:>12}{:>12}{:>12}",
+                interface, counts.sent, counts.dropped, counts.errors
+            ));
+        }
+    }
+
+    fn print_latency(&mut self) {
+        if !latency::enabled() {
+            self.print("Latency measurement is not running. Run `latency on`\r\n");
+        }
+
+        self.println(format_args!(
+            "{:<10}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            "stage", "count", "min", "avg", "max", "p99"
+        ));
+        for (name, stage) in LatencyStage::ALL {
+            let summary = latency::summary(stage);
+            self.println(format_args!(
+                "{:<10}{:>8}{:>8}{:>8}{:>8}{:>8}",
+                name, summary.count, summary.min, summary.avg, summary.max, summary.p99
+            ));
+        }
+    }
+
+    fn print_usage(&mut self) {
+        let stats = usage::get();
+
+        self.println(format_args!("{:<10}{:>12}", "button", "presses"));
+        for (name, presses) in BUTTONS.iter().zip(stats.presses) {
+            self.println(format_args!("{:<10}{:>12}", name, presses));
+        }
+
+        self.println(format_args!("{:<10}{:>12}", "knob", "turns"));
+        for (name, travel) in ["left", "right"].iter().zip(stats.knob_travel) {
+            self.println(format_args!(
+                "{:<10}{:>9}.{:02}",
+                name,
+                travel / UsageStats::KNOB_TURN,
+                travel % UsageStats::KNOB_TURN * 100 / UsageStats::KNOB_TURN
+            ));
+        }
+    }
+
+    fn print_chatter(&mut self) {
+        self.println(format_args!(
+            "{:<10}{:>10}{:>10}{:>14}{:>8}{:>10}",
+            "button", "releases", "bounces", "per release", "max ms", "warnings"
+        ));
+        for (i, name) in BUTTONS.iter().chain(&AUX_BUTTONS).enumerate() {
+            let Some(stats) = chatter::stats(i) else {
+                continue;
+            };
+
+            // Bounces per release in hundredths
+            let per_release = (stats.bounces as u64 * 100)
+                .checked_div(stats.releases as u64)
+                .unwrap_or(0);
+            self.println(format_args!(
+                "{:<10}{:>10}{:>10}{:>11}.{:02}{:>8}{:>10}",
+                name,
+                stats.releases,
+                stats.bounces,
+                per_release / 100,
+                per_release % 100,
+                stats.max_duration_ms,
+                stats.warnings
+            ));
+        }
+    }
+
+    /// Set userdata value. Returns `None` if name or value is invalid.
+    fn set(&mut self, name: &str, value: &str) -> Option<()> {
+        match name {
+            "log.format" => {
+                let format = find(&LOG_FORMATS, value)?;
+                userdata::update(|data| data.log_format = format);
+            }
+
+            "led.brightness" => {
+                let brightness = value.parse().ok()?;
+                userdata::update(|data| data.led.brightness = brightness);
+            }
+
+            "led.effect" => {
+                let effect = find(&LED_EFFECTS, value)?;
+                userdata::update(|data| data.led.effect = effect);
+            }
+
+            "knob.left" => {
+                let axis = find(&STICK_AXES, value)?;
+                userdata::update(|data| data.knob_axes.left_knob = axis);
+            }
+
+            "knob.right" => {
+                let axis = find(&STICK_AXES, value)?;
+                userdata::update(|data| data.knob_axes.right_knob = axis);
+            }
+
+            "midi.channel" => {
+                let channel = value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|ch| (1..=16).contains(ch))?;
+                userdata::update(|data| data.midi.channel = channel - 1);
+            }
+
+            "midi.knob_mode" => {
+                let mode = find(&MIDI_KNOB_MODES, value)?;
+                userdata::update(|data| data.midi.knob_mode = mode);
+            }
+
+            "midi.left_cc" => {
+                let cc = parse_midi_value(value)?;
+                userdata::update(|data| data.midi.left_knob_cc = cc);
+            }
+
+            "midi.right_cc" => {
+                let cc = parse_midi_value(value)?;
+                userdata::update(|data| data.midi.right_knob_cc = cc);
+            }
+
+            "tapping_term" => {
+                let ms = value.parse::<u16>().ok().filter(|ms| *ms != 0)?;
+                userdata::update(|data| data.tapping_term_ms = ms);
+            }
+
+            "combo_term" => {
+                let ms = value.parse::<u16>().ok().filter(|ms| *ms != 0)?;
+                userdata::update(|data| data.combo_term_ms = ms);
+            }
+
+            _ => {
+                if let Some(button) = name.strip_prefix("midi.note.") {
+                    let index = BUTTONS.iter().position(|name| *name == button)?;
+                    let note = parse_midi_value(value)?;
+                    userdata::update(|data| data.midi.notes[index] = note);
+                } else if let Some(mode) = name.strip_prefix("hotkey.") {
+                    let mode = find(&MODES, mode)?;
+                    let hotkey = parse_buttons(value)?;
+                    userdata::update(|data| data.mode_hotkeys[mode as usize] = hotkey);
+                } else if let Some(interface) = name.strip_prefix("poll.") {
+                    let field = find(&PollIntervals::FIELDS, interface)?;
+                    let ms = value.parse::<u8>().ok().filter(|ms| *ms != 0)?;
+                    userdata::update(|data| *field(&mut data.poll) = ms);
+                } else if let Some(key) = name.strip_prefix("keymap.") {
+                    let field = find(&KEYMAP, key)?;
+                    let code = parse_keycode(value)?;
+                    userdata::update(|data| *field(&mut data.keymaps[0]) = code);
+                } else {
+                    return None;
+                }
+            }
+        }
+
+        self.println(format_args!("{} = {}", name, value));
+        Some(())
+    }
+}
+
+fn find<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(entry, _)| *entry == name)
+        .map(|(_, value)| *value)
+}
+
+fn name<T: PartialEq>(table: &[(&'static str, T)], value: T) -> &'static str {
+    table
+        .iter()
+        .find(|(_, entry)| *entry == value)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// Button mask shown as button names joined with `+`, or `none`
+struct ButtonNames(u16);
+
+impl fmt::Display for ButtonNames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("none");
+        }
+
+        let mut names = BUTTONS
+            .iter()
+            .chain(&AUX_BUTTONS)
+            .enumerate()
+            .filter(|(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, name)| name);
+        if let Some(first) = names.next() {
+            f.write_str(first)?;
+        }
+        for name in names {
+            write!(f, "+{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse button names joined with `+` (e.g. `start+button1`) into [`ButtonId`] mask.
+/// `none` is an empty mask.
+fn parse_buttons(value: &str) -> Option<u16> {
+    if value == "none" {
+        return Some(0);
+    }
+
+    value.split('+').try_fold(0, |mask, button| {
+        let id = BUTTONS
+            .iter()
+            .chain(&AUX_BUTTONS)
+            .position(|name| *name == button)?;
+        Some(mask | 1 << id)
+    })
+}
+
+/// Parse 7 bits MIDI data value
+fn parse_midi_value(value: &str) -> Option<u8> {
+    value.parse().ok().filter(|value| *value <= 0x7F)
+}
+
+/// Parse keycode in hex (0x prefixed) or decimal
+fn parse_keycode(value: &str) -> Option<Keycode> {
+    let code = match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+
+    Some(Keycode(code))
+}
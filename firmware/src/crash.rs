@@ -0,0 +1,148 @@
+//! Crash record kept in retained RAM across resets.
+//! Panics and watchdog stalls write the reason here before the chip is reset.
+
+use core::{
+    fmt::{self, Write},
+    mem::MaybeUninit,
+    panic::PanicInfo,
+    ptr, str,
+};
+
+use embassy_rp::pac;
+use heapless::String;
+
+use crate::{chip, log};
+
+const MAGIC: u32 = 0xc4a5_11ed;
+pub const CRASH_TEXT_LEN: usize = 120;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CrashRecord {
+    magic: u32,
+    len: u32,
+    text: [u8; CRASH_TEXT_LEN],
+    checksum: u32,
+}
+
+impl CrashRecord {
+    fn checksum(&self) -> u32 {
+        self.text[..self.len as usize]
+            .iter()
+            .fold(self.magic ^ self.len, |sum, &b| {
+                sum.rotate_left(5) ^ b as u32
+            })
+    }
+
+    fn text(&self) -> Option<&str> {
+        if self.magic != MAGIC
+            || self.len as usize > CRASH_TEXT_LEN
+            || self.checksum != self.checksum()
+        {
+            return None;
+        }
+
+        str::from_utf8(&self.text[..self.len as usize]).ok()
+    }
+}
+
+/// Not initialized on boot, so the record survives watchdog and software resets.
+/// Contents are garbage after power on, which the magic and checksum reject.
+#[unsafe(link_section = ".uninit.crash")]
+static mut CRASH: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+/// Writer truncating text that does not fit in the record
+struct TextWriter {
+    text: [u8; CRASH_TEXT_LEN],
+    len: usize,
+}
+
+impl Write for TextWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let mut buf = [0; 4];
+            let bytes = c.encode_utf8(&mut buf).as_bytes();
+            let Some(dest) = self.text.get_mut(self.len..self.len + bytes.len()) else {
+                return Err(fmt::Error);
+            };
+
+            dest.copy_from_slice(bytes);
+            self.len += bytes.len();
+        }
+
+        Ok(())
+    }
+}
+
+/// Write crash reason to the record
+pub fn record(args: fmt::Arguments) {
+    let mut writer = TextWriter {
+        text: [0; _],
+        len: 0,
+    };
+    _ = writer.write_fmt(args);
+
+    let mut record = CrashRecord {
+        magic: MAGIC,
+        len: writer.len as u32,
+        text: writer.text,
+        checksum: 0,
+    };
+    record.checksum = record.checksum();
+
+    // Written without locking, as this also runs in the panic handler
+    unsafe { ptr::write_volatile(&raw mut CRASH, MaybeUninit::new(record)) };
+}
+
+/// Read last crash reason, if any
+pub fn last() -> Option<String<CRASH_TEXT_LEN>> {
+    // SAFETY: every bit pattern is a valid `CrashRecord`
+    let record = unsafe { ptr::read_volatile(&raw const CRASH).assume_init() };
+
+    record.text().and_then(|text| String::try_from(text).ok())
+}
+
+/// Forget last crash reason
+pub fn clear() {
+    unsafe {
+        let record = (&raw mut CRASH).cast::<CrashRecord>();
+        ptr::write_volatile(&raw mut (*record).magic, 0);
+    }
+}
+
+/// Log crash reason recorded before last reset
+pub fn init() {
+    if let Some(text) = last() {
+        log::warn!("Recovered from crash: {}", text.as_str());
+    }
+}
+
+/// Reset the whole chip through the watchdog.
+/// RAM is not cleared, so the crash record is kept.
+pub fn reset() -> ! {
+    pac::PSM
+        .wdsel()
+        .write_value(pac::psm::regs::Wdsel(chip::WATCHDOG_RESET_SELECT));
+    pac::WATCHDOG.ctrl().write(|w| w.set_trigger(true));
+
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+
+    match info.location() {
+        Some(location) => record(format_args!(
+            "panic at {}:{}: {}",
+            location.file(),
+            location.line(),
+            info.message()
+        )),
+        None => record(format_args!("panic: {}", info.message())),
+    }
+
+    reset()
+}
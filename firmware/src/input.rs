@@ -1,27 +1,65 @@
 mod builder;
+pub mod chatter;
+mod combo;
 pub mod config;
+pub mod hotkey;
 mod key;
+pub mod latency;
+mod layer;
 pub mod reader;
-mod report;
+pub mod report;
 mod ticker;
 
 use core::cell::Cell;
 
 use embassy_executor::{SpawnToken, Spawner};
-use embassy_futures::join::join;
+use embassy_futures::{
+    join::join,
+    select::{Either3, select3},
+};
 use embassy_rp::gpio::Level;
-use embassy_sync::blocking_mutex::{NoopMutex, ThreadModeMutex, raw::NoopRawMutex};
-use embassy_time::Instant;
+use embassy_sync::{
+    blocking_mutex::{NoopMutex, ThreadModeMutex, raw::NoopRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Instant, Ticker, Timer};
+use filter::KnobValue;
 
 use crate::{
     input::{
-        key::InputReports,
-        reader::{InputRead, button::ButtonInputReader, knob::KnobInputReader},
+        combo::Combos,
+        config::{INPUT_ACTIVE_TICK, INPUT_IDLE_RESYNC, KNOB_SAMPLE_INTERVAL},
+        hotkey::{
+            DIAGNOSTICS_HOTKEY, DIAGNOSTICS_STOP_HOLD, HoldHotkey, RETURN_HID_HOLD,
+            RETURN_HID_HOTKEY,
+        },
+        layer::LayerState,
+        reader::{
+            InputRead,
+            button::{ButtonId, ButtonInputReader, MAIN_BUTTON_COUNT},
+            knob::KnobInputReader,
+        },
         ticker::ElapsedTimer,
     },
+    keycodes,
     led::{self, LedState},
-    usb::{Driver, eac::EacInputReport},
-    userdata::{self, keymap::Keymap},
+    log,
+    usb::{
+        Driver,
+        eac::EacInputReport,
+        hid::GamepadInputReport,
+        midi::{self, MidiPacket},
+        switch::SwitchInputReport,
+        xinput::{XInputButton, XInputReport},
+    },
+    userdata::{
+        self, UsbMode,
+        keymap::{Keymap, LAYER_COUNT},
+        midi::{MidiKnobMode, MidiSettings},
+        stick::{KnobAxes, StickAxis},
+        usage::{self, UsageStats},
+    },
+    watchdog::{self, Heartbeat},
 };
 
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
@@ -53,7 +91,17 @@ pub fn eac_input_task(
         button_reader: ButtonInputReader<'static>,
         knob_reader: KnobInputReader<'static>,
     ) {
-        input_read_loop(button_reader, knob_reader, report_eac_inputs).await;
+        let mut return_hotkey = HoldHotkey::new(RETURN_HID_HOTKEY, RETURN_HID_HOLD);
+        // Other combos would only hold back buttons without reporting anything
+        let mut combos =
+            Combos::with_outputs(|code| matches!(code, keycodes::EAC_SERVICE | keycodes::EAC_TEST));
+        let mut timer = ElapsedTimer::new(Instant::now());
+        input_read_loop(button_reader, knob_reader, |read| {
+            read_return_hotkey(&mut return_hotkey, read);
+            report_eac_inputs(&mut combos, timer.next_elapsed_ms(), read);
+            combos.pending()
+        })
+        .await;
     }
 
     spawner.must_spawn(report::eac_report_task(builder));
@@ -71,28 +119,21 @@ pub fn hid_input_task(
         button_reader: ButtonInputReader<'static>,
         knob_reader: KnobInputReader<'static>,
     ) {
-        let keymap = NoopMutex::const_new(
+        let keymaps = NoopMutex::const_new(
             NoopRawMutex::new(),
-            userdata::get(|userdata| userdata.keymap.clone()),
+            userdata::get(|userdata| userdata.keymaps.clone()),
         );
+        let mut layers = LayerState::new();
 
         let hid_input_updater = input_read_loop(button_reader, knob_reader, |read| {
-            keymap.lock(|keymap| {
-                report_hid_inputs(keymap, read);
-
-                led::update(LedState {
-                    button_1: read.buttons.button1,
-                    button_2: read.buttons.button2,
-                    button_3: read.buttons.button3,
-                    button_4: read.buttons.button4,
-                    fx_1: read.buttons.fx1,
-                    fx_2: read.buttons.fx2,
-                    start: read.buttons.start,
-                });
+            keymaps.lock(|keymaps| {
+                report_hid_inputs(keymaps, &mut layers, read);
+                update_led(read);
             });
+            layers.pending()
         });
 
-        join(hid_input_updater, keymap_updater(&keymap)).await;
+        join(hid_input_updater, keymap_updater(&keymaps)).await;
     }
 
     spawner.must_spawn(report::gamepad_report_task(builder));
@@ -101,85 +142,481 @@ pub fn hid_input_task(
     inner(button_reader, knob_reader)
 }
 
+pub fn xinput_input_task(
+    spawner: Spawner,
+    button_reader: ButtonInputReader<'static>,
+    knob_reader: KnobInputReader<'static>,
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(
+        button_reader: ButtonInputReader<'static>,
+        knob_reader: KnobInputReader<'static>,
+    ) {
+        let keymaps = NoopMutex::const_new(
+            NoopRawMutex::new(),
+            userdata::get(|userdata| userdata.keymaps.clone()),
+        );
+        let mut layers = LayerState::new();
+
+        let mut return_hotkey = HoldHotkey::new(RETURN_HID_HOTKEY, RETURN_HID_HOLD);
+        let xinput_input_updater = input_read_loop(button_reader, knob_reader, |read| {
+            read_return_hotkey(&mut return_hotkey, read);
+            let axes = userdata::get(|userdata| userdata.knob_axes);
+            keymaps.lock(|keymaps| {
+                report_xinput_inputs(keymaps, &mut layers, &axes, read);
+                update_led(read);
+            });
+            layers.pending()
+        });
+
+        join(xinput_input_updater, keymap_updater(&keymaps)).await;
+    }
+
+    spawner.must_spawn(report::xinput_report_task(builder));
+    inner(button_reader, knob_reader)
+}
+
+pub fn switch_input_task(
+    spawner: Spawner,
+    button_reader: ButtonInputReader<'static>,
+    knob_reader: KnobInputReader<'static>,
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(
+        button_reader: ButtonInputReader<'static>,
+        knob_reader: KnobInputReader<'static>,
+    ) {
+        let keymaps = NoopMutex::const_new(
+            NoopRawMutex::new(),
+            userdata::get(|userdata| userdata.keymaps.clone()),
+        );
+        let mut layers = LayerState::new();
+
+        let mut return_hotkey = HoldHotkey::new(RETURN_HID_HOTKEY, RETURN_HID_HOLD);
+        let switch_input_updater = input_read_loop(button_reader, knob_reader, |read| {
+            read_return_hotkey(&mut return_hotkey, read);
+            let axes = userdata::get(|userdata| userdata.knob_axes);
+            keymaps.lock(|keymaps| {
+                report_switch_inputs(keymaps, &mut layers, &axes, read);
+                update_led(read);
+            });
+            layers.pending()
+        });
+
+        join(switch_input_updater, keymap_updater(&keymaps)).await;
+    }
+
+    spawner.must_spawn(report::switch_report_task(builder));
+    inner(button_reader, knob_reader)
+}
+
+pub fn midi_input_task(
+    spawner: Spawner,
+    button_reader: ButtonInputReader<'static>,
+    knob_reader: KnobInputReader<'static>,
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(
+        button_reader: ButtonInputReader<'static>,
+        knob_reader: KnobInputReader<'static>,
+    ) {
+        let mut return_hotkey = HoldHotkey::new(RETURN_HID_HOTKEY, RETURN_HID_HOLD);
+        let mut last = MidiInputState::DEFAULT;
+        input_read_loop(button_reader, knob_reader, |read| {
+            read_return_hotkey(&mut return_hotkey, read);
+            let settings = userdata::get(|userdata| userdata.midi);
+            report_midi_inputs(&settings, &mut last, read);
+            update_led(read);
+            false
+        })
+        .await;
+    }
+
+    spawner.must_spawn(report::midi_report_task(builder));
+    inner(button_reader, knob_reader)
+}
+
 pub static CURRENT_INPUT: ThreadModeMutex<Cell<InputRead>> =
     ThreadModeMutex::new(Cell::new(InputRead::DEFAULT));
 
 async fn input_read_loop(
     mut button_reader: ButtonInputReader<'static>,
     mut knob_reader: KnobInputReader<'static>,
-    mut f: impl FnMut(InputRead),
+    mut f: impl FnMut(InputRead) -> bool,
 ) {
-    CURRENT_INPUT.borrow().set(InputRead {
+    let initial = InputRead {
         knobs: knob_reader.read(0).await,
         buttons: button_reader.read(0),
-    });
+    };
+    CURRENT_INPUT.borrow().set(initial);
 
-    let mut ticker = ElapsedTimer::new(Instant::now());
-    loop {
-        let read = CURRENT_INPUT.borrow().get();
-        f(read);
+    let knob_signal = Signal::<NoopRawMutex, _>::new();
+    let knob_sampler = knob_sample_loop(&mut knob_reader, &knob_signal, initial.knobs);
 
+    let button_updater = async {
+        let mut ticker = ElapsedTimer::new(Instant::now());
+        let mut knobs = initial.knobs;
+        // Boot hotkey may still be held
+        let mut diagnostics_hotkey =
+            HoldHotkey::new(DIAGNOSTICS_HOTKEY, DIAGNOSTICS_STOP_HOLD).after_release();
         loop {
-            let elapsed_ms = ticker.next_elapsed_ms();
-            let next = InputRead {
-                knobs: knob_reader.read(elapsed_ms).await,
-                buttons: button_reader.read(elapsed_ms),
-            };
+            let read = CURRENT_INPUT.borrow().get();
+            let pending = f(read);
+            latency::discard_unclaimed();
+
+            if led::diagnostics_running() {
+                update_diagnostics_led(read);
 
-            if next != read || next != InputRead::DEFAULT {
-                CURRENT_INPUT.borrow().set(next);
-                break;
+                // Only way out in modes without Via or console
+                if diagnostics_hotkey.update(&read.buttons, Instant::now()) {
+                    log::info!("LED diagnostics stopped via hotkey.");
+                    led::stop_diagnostics();
+                }
+            }
+
+            loop {
+                watchdog::heartbeat(Heartbeat::Input);
+
+                // Button edges wake the loop directly and never wait for ADC conversion.
+                // While inputs are active or keys are pending, keep ticking so release debounce,
+                // hold hotkeys and tap/hold keys advance.
+                let timeout = if read.active() || pending {
+                    INPUT_ACTIVE_TICK
+                } else {
+                    INPUT_IDLE_RESYNC
+                };
+                if let Either3::Second(next_knobs) = select3(
+                    button_reader.wait_for_edge(),
+                    knob_signal.wait(),
+                    Timer::after(timeout),
+                )
+                .await
+                {
+                    knobs = next_knobs;
+                }
+
+                let next = InputRead {
+                    knobs,
+                    buttons: button_reader.read(ticker.next_elapsed_ms()),
+                };
+
+                if next != read || next.active() || pending {
+                    count_usage(&read, &next);
+                    CURRENT_INPUT.borrow().set(next);
+                    break;
+                }
             }
         }
+    };
+
+    join(knob_sampler, button_updater).await;
+}
+
+/// Sample knobs on their own cadence, signaling filtered values when they change
+async fn knob_sample_loop(
+    knob_reader: &mut KnobInputReader<'static>,
+    signal: &Signal<NoopRawMutex, (KnobValue, KnobValue)>,
+    mut last: (KnobValue, KnobValue),
+) {
+    let mut ticker = Ticker::every(KNOB_SAMPLE_INTERVAL);
+    let mut elapsed = ElapsedTimer::new(Instant::now());
+    loop {
+        ticker.next().await;
+
+        let knobs = knob_reader.read(elapsed.next_elapsed_ms()).await;
+        if knobs != last {
+            last = knobs;
+            signal.signal(knobs);
+        }
     }
 }
 
-async fn keymap_updater(keymap: &NoopMutex<Keymap>) {
+/// Count button presses and knob travel between reads
+fn count_usage(read: &InputRead, next: &InputRead) {
+    let pressed = next.buttons.mask() & !read.buttons.mask();
+    let travel = |prev: KnobValue, next: KnobValue| {
+        let turn = UsageStats::KNOB_TURN as i32;
+        let delta = (next.absolute as i32 - prev.absolute as i32).rem_euclid(turn);
+        delta.min(turn - delta) as u32
+    };
+
+    usage::count(
+        pressed,
+        [
+            travel(read.knobs.0, next.knobs.0),
+            travel(read.knobs.1, next.knobs.1),
+        ],
+    );
+}
+
+async fn keymap_updater(keymaps: &NoopMutex<[Keymap; LAYER_COUNT]>) {
     let mut listener = userdata::listener();
     loop {
         listener.changed().await;
 
-        let new_keymap = userdata::get(|userdata| userdata.keymap.clone());
+        let new_keymaps = userdata::get(|userdata| userdata.keymaps.clone());
         unsafe {
-            keymap.lock_mut(|keymap| {
-                *keymap = new_keymap;
+            keymaps.lock_mut(|keymaps| {
+                *keymaps = new_keymaps;
             });
         }
     }
 }
 
-fn report_eac_inputs(input: InputRead) {
+/// Return to HID mode if the hotkey is held
+fn read_return_hotkey(hotkey: &mut HoldHotkey, input: InputRead) {
+    if !hotkey.update(&input.buttons, Instant::now()) {
+        return;
+    }
+
+    log::info!("HID mode enabled via hotkey.");
+    userdata::update(|data| {
+        data.mode = UsbMode::Hid;
+    });
+    userdata::save_and_reboot();
+}
+
+fn update_led(input: InputRead) {
+    let buttons = input.buttons;
+    led::update(LedState {
+        button_1: buttons.level(ButtonId::BUTTON1),
+        button_2: buttons.level(ButtonId::BUTTON2),
+        button_3: buttons.level(ButtonId::BUTTON3),
+        button_4: buttons.level(ButtonId::BUTTON4),
+        fx_1: buttons.level(ButtonId::FX1),
+        fx_2: buttons.level(ButtonId::FX2),
+        start: buttons.level(ButtonId::START),
+    });
+}
+
+/// Mirror inputs to lamps for wiring check.
+/// Knob movements are shown on FX lamps of the same side.
+fn update_diagnostics_led(input: InputRead) {
+    let buttons = input.buttons;
+    led::update_diagnostics(LedState {
+        button_1: buttons.level(ButtonId::BUTTON1),
+        button_2: buttons.level(ButtonId::BUTTON2),
+        button_3: buttons.level(ButtonId::BUTTON3),
+        button_4: buttons.level(ButtonId::BUTTON4),
+        fx_1: Level::from(buttons.pressed(ButtonId::FX1) || input.knobs.0.delta != 0),
+        fx_2: Level::from(buttons.pressed(ButtonId::FX2) || input.knobs.1.delta != 0),
+        start: buttons.level(ButtonId::START),
+    });
+}
+
+/// Report buttons in EAC order.
+/// Service and test are pressed by combos with [`keycodes::EAC_SERVICE`] and [`keycodes::EAC_TEST`] outputs,
+/// or by auxiliary buttons with these keycodes on the base layer.
+fn report_eac_inputs(combos: &mut Combos, elapsed_ms: u16, input: InputRead) {
+    const SERVICE: u16 = 1 << 6;
+    const TEST: u16 = 1 << 7;
+    const START: u16 = 1 << 8;
+
+    let base = userdata::get(|data| data.keymaps[0].clone());
+    let (mask, outputs) = combos.update(&base, input.buttons.mask(), elapsed_ms);
+
+    // BT-A to FX-R keep their mask bits
+    let mut buttons = (mask & 0x3F) as u16;
+    if mask & (1 << ButtonId::START) != 0 {
+        buttons |= START;
+    }
+    let aux = base
+        .buttons()
+        .into_iter()
+        .enumerate()
+        .skip(MAIN_BUTTON_COUNT)
+        .map(|(i, code)| (code, mask & (1 << i) != 0));
+    for (code, pressed) in outputs.chain(aux) {
+        match code {
+            keycodes::EAC_SERVICE if pressed => buttons |= SERVICE,
+            keycodes::EAC_TEST if pressed => buttons |= TEST,
+            _ => {}
+        }
+    }
+
     report::EAC.signal(EacInputReport {
         report_id: 4,
-        buttons: input.buttons.button1 as u16
-            | ((input.buttons.button2 as u16) << 1)
-            | ((input.buttons.button3 as u16) << 2)
-            | ((input.buttons.button4 as u16) << 3)
-            | ((input.buttons.fx1 as u16) << 4)
-            | ((input.buttons.fx2 as u16) << 5)
-            | ((input.buttons.start as u16) << 8),
+        buttons,
         x: (input.knobs.0.absolute >> 4) as u8,
         y: (input.knobs.1.absolute >> 4) as u8,
     });
 }
 
-fn report_hid_inputs(keymap: &Keymap, input: InputRead) {
-    let mut reports = InputReports::default();
+fn report_hid_inputs(keymaps: &[Keymap; LAYER_COUNT], layers: &mut LayerState, input: InputRead) {
+    layers.map(keymaps, input).send();
+}
+
+/// Gamepad buttons from button 1 to button 16 in XInput button order
+const XINPUT_BUTTON_MAP: [u16; 16] = [
+    XInputButton::A,
+    XInputButton::B,
+    XInputButton::X,
+    XInputButton::Y,
+    XInputButton::LEFT_SHOULDER,
+    XInputButton::RIGHT_SHOULDER,
+    XInputButton::BACK,
+    XInputButton::START,
+    XInputButton::LEFT_THUMB,
+    XInputButton::RIGHT_THUMB,
+    XInputButton::GUIDE,
+    0,
+    0,
+    0,
+    0,
+    0,
+];
+
+fn report_xinput_inputs(
+    keymaps: &[Keymap; LAYER_COUNT],
+    layers: &mut LayerState,
+    axes: &KnobAxes,
+    input: InputRead,
+) {
+    let gamepad = layers.map(keymaps, input).into_gamepad();
+
+    let mut report = XInputReport::DEFAULT;
+    report.buttons = xinput_buttons(&gamepad).into();
+    for (knob, axis) in [
+        (input.knobs.0, axes.left_knob),
+        (input.knobs.1, axes.right_knob),
+    ] {
+        // Scale 12 bits absolute value to full axis range
+        let value = ((knob.absolute as i32 * 16) + i16::MIN as i32) as i16;
+        match axis {
+            StickAxis::LeftX => report.left_x = value.into(),
+            StickAxis::LeftY => report.left_y = value.into(),
+            StickAxis::RightX => report.right_x = value.into(),
+            StickAxis::RightY => report.right_y = value.into(),
+        }
+    }
+
+    report::XINPUT.signal(report);
+}
+
+fn report_switch_inputs(
+    keymaps: &[Keymap; LAYER_COUNT],
+    layers: &mut LayerState,
+    axes: &KnobAxes,
+    input: InputRead,
+) {
+    let gamepad = layers.map(keymaps, input).into_gamepad();
+
+    let mut report = SwitchInputReport {
+        // Switch buttons are in gamepad button order
+        buttons: gamepad.buttons,
+        hat: match gamepad.dpad {
+            1..=8 => gamepad.dpad - 1,
+            _ => SwitchInputReport::HAT_CENTER,
+        },
+        ..SwitchInputReport::DEFAULT
+    };
+    for (knob, axis) in [
+        (input.knobs.0, axes.left_knob),
+        (input.knobs.1, axes.right_knob),
+    ] {
+        let value = (knob.absolute >> 4) as u8;
+        match axis {
+            StickAxis::LeftX => report.left_x = value,
+            StickAxis::LeftY => report.left_y = value,
+            StickAxis::RightX => report.right_x = value,
+            StickAxis::RightY => report.right_y = value,
+        }
+    }
+
+    report::SWITCH.signal(report);
+}
+
+/// Last inputs sent as MIDI events
+struct MidiInputState {
+    /// Button mask. See [`hotkey::HotkeyButton`]
+    buttons: u16,
+    /// Knob positions (0-127). `None` until first read.
+    knobs: Option<[u8; 2]>,
+}
+
+impl MidiInputState {
+    const DEFAULT: Self = Self {
+        buttons: 0,
+        knobs: None,
+    };
+}
+
+fn report_midi_inputs(settings: &MidiSettings, last: &mut MidiInputState, input: InputRead) {
+    let channel = settings.channel;
+
+    let buttons = input.buttons.mask();
+    let changed = buttons ^ last.buttons;
+    for (i, note) in settings.notes.iter().enumerate() {
+        if changed & (1 << i) == 0 {
+            continue;
+        }
+
+        send_midi(if buttons & (1 << i) != 0 {
+            midi::note_on(channel, *note)
+        } else {
+            midi::note_off(channel, *note)
+        });
+    }
+    last.buttons = buttons;
+
+    // Scale 12 bits absolute value to 7 bits
+    let knobs = [
+        (input.knobs.0.absolute >> 5) as u8,
+        (input.knobs.1.absolute >> 5) as u8,
+    ];
+    if let Some(last_knobs) = last.knobs {
+        for ((knob, last_knob), cc) in knobs
+            .into_iter()
+            .zip(last_knobs)
+            .zip([settings.left_knob_cc, settings.right_knob_cc])
+        {
+            if knob == last_knob {
+                continue;
+            }
+
+            let value = match settings.knob_mode {
+                MidiKnobMode::Absolute => knob,
+                MidiKnobMode::Relative => {
+                    // Signed 7 bits difference, wrapping around on every turn
+                    let step = ((knob.wrapping_sub(last_knob) << 1) as i8) >> 1;
+                    (64 + step).clamp(1, 127) as u8
+                }
+            };
+            send_midi(midi::control_change(channel, cc, value));
+        }
+    }
+    last.knobs = Some(knobs);
+}
+
+#[inline]
+fn send_midi(packet: MidiPacket) {
+    if report::MIDI.try_send(packet).is_err() {
+        report::MIDI_STATS.add_dropped();
+        log::warn!("MIDI event queue is full. Event dropped.");
+    }
+}
 
-    reports.key(keymap.button1, input.buttons.button1 == Level::High);
-    reports.key(keymap.button2, input.buttons.button2 == Level::High);
-    reports.key(keymap.button3, input.buttons.button3 == Level::High);
-    reports.key(keymap.button4, input.buttons.button4 == Level::High);
-    reports.key(keymap.fx1, input.buttons.fx1 == Level::High);
-    reports.key(keymap.fx2, input.buttons.fx2 == Level::High);
-    reports.key(keymap.start, input.buttons.start == Level::High);
-    let left_knob = KnobTurn::from(input.knobs.0.delta);
-    reports.key(keymap.left_knob_left, left_knob == KnobTurn::Left);
-    reports.key(keymap.left_knob_right, left_knob == KnobTurn::Right);
+fn xinput_buttons(gamepad: &GamepadInputReport) -> u16 {
+    let mut buttons = XINPUT_BUTTON_MAP
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| gamepad.buttons & (1 << i) != 0)
+        .fold(0, |buttons, (_, bit)| buttons | bit);
 
-    let right_knob = KnobTurn::from(input.knobs.1.delta);
-    reports.key(keymap.right_knob_left, right_knob == KnobTurn::Left);
-    reports.key(keymap.right_knob_right, right_knob == KnobTurn::Right);
+    buttons |= match gamepad.dpad {
+        1 => XInputButton::DPAD_UP,
+        2 => XInputButton::DPAD_UP | XInputButton::DPAD_RIGHT,
+        3 => XInputButton::DPAD_RIGHT,
+        4 => XInputButton::DPAD_DOWN | XInputButton::DPAD_RIGHT,
+        5 => XInputButton::DPAD_DOWN,
+        6 => XInputButton::DPAD_DOWN | XInputButton::DPAD_LEFT,
+        7 => XInputButton::DPAD_LEFT,
+        8 => XInputButton::DPAD_UP | XInputButton::DPAD_LEFT,
+        _ => 0,
+    };
 
-    reports.send();
+    buttons
 }
@@ -164,6 +164,43 @@ impl KeyboardInputBuilder {
         self.inner.modifier |= 1 << n;
     }
 
+    /// Add HID modifier bits
+    #[inline]
+    pub fn modifiers(&mut self, bits: u8) {
+        self.inner.modifier |= bits;
+    }
+
+    /// Pressed HID modifier bits
+    #[inline]
+    pub const fn modifier_bits(&self) -> u8 {
+        self.inner.modifier
+    }
+
+    /// Release HID modifier bits
+    #[inline]
+    pub fn release_modifiers(&mut self, bits: u8) {
+        self.inner.modifier &= !bits;
+    }
+
+    /// Whether key usage `code` is pressed
+    #[inline]
+    pub fn pressed(&self, code: u8) -> bool {
+        self.inner.keycodes[..self.next_key_index].contains(&code)
+    }
+
+    /// Release key usage `code`. Returns whether it was pressed
+    pub fn release(&mut self, code: u8) -> bool {
+        let keys = &mut self.inner.keycodes[..self.next_key_index];
+        let Some(index) = keys.iter().position(|key| *key == code) else {
+            return false;
+        };
+
+        keys.copy_within(index + 1.., index);
+        self.next_key_index -= 1;
+        self.inner.keycodes[self.next_key_index] = 0;
+        true
+    }
+
     #[inline]
     pub const fn build(self) -> KeyboardReport {
         self.inner
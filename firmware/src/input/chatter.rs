@@ -0,0 +1,82 @@
+//! Switch chatter statistics per button.
+//! Bounces are measured on raw edges, including ones the debouncer absorbs.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use filter::Bounce;
+
+use crate::{
+    input::{config::DEBOUNCE_MS, reader::button::BUTTON_COUNT},
+    log,
+};
+
+/// Bounces in one transition to warn about
+const WARN_BOUNCES: u16 = 3;
+/// Bouncing longer than debounce time may cause misfires
+const WARN_DURATION_MS: u16 = DEBOUNCE_MS;
+
+/// Buttons in [`ButtonId`](super::reader::button::ButtonId) order
+const BUTTON_NAMES: [&str; BUTTON_COUNT] = [
+    "BT-A", "BT-B", "BT-C", "BT-D", "FX-L", "FX-R", "Start", "Aux1", "Aux2", "Aux3", "Aux4",
+];
+
+#[derive(Clone, Copy)]
+pub struct ChatterStats {
+    pub releases: u32,
+    /// Total bounces of presses and releases
+    pub bounces: u32,
+    /// Longest bouncing
+    pub max_duration_ms: u16,
+    /// Transitions exceeding warning threshold
+    pub warnings: u32,
+}
+
+impl ChatterStats {
+    const DEFAULT: Self = Self {
+        releases: 0,
+        bounces: 0,
+        max_duration_ms: 0,
+        warnings: 0,
+    };
+}
+
+static CHATTER: Mutex<CriticalSectionRawMutex, RefCell<[ChatterStats; BUTTON_COUNT]>> =
+    Mutex::new(RefCell::new([ChatterStats::DEFAULT; BUTTON_COUNT]));
+
+/// Get statistics of button at index
+pub fn stats(button: usize) -> Option<ChatterStats> {
+    CHATTER.lock(|chatter| chatter.borrow().get(button).copied())
+}
+
+pub fn reset() {
+    CHATTER.lock(|chatter| *chatter.borrow_mut() = [ChatterStats::DEFAULT; BUTTON_COUNT]);
+}
+
+/// Record debounced release of button at index
+pub fn released(button: usize) {
+    CHATTER.lock(|chatter| chatter.borrow_mut()[button].releases += 1);
+}
+
+/// Record bounces of button at index, warning if over threshold
+pub fn bounced(button: usize, bounce: Bounce) {
+    let warn = bounce.bounces >= WARN_BOUNCES || bounce.duration_ms >= WARN_DURATION_MS;
+
+    CHATTER.lock(|chatter| {
+        let stats = &mut chatter.borrow_mut()[button];
+        stats.bounces = stats.bounces.saturating_add(bounce.bounces as u32);
+        stats.max_duration_ms = stats.max_duration_ms.max(bounce.duration_ms);
+        if warn {
+            stats.warnings += 1;
+        }
+    });
+
+    if warn {
+        log::warn!(
+            "{} switch chatter: {} bounces in {}ms. Consider replacing the switch.",
+            BUTTON_NAMES[button],
+            bounce.bounces,
+            bounce.duration_ms
+        );
+    }
+}
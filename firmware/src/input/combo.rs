@@ -0,0 +1,93 @@
+//! Combos of userdata, shared by keymap mapping and EAC mode.
+
+use keycode::Keycode;
+
+use filter::ComboResolver;
+
+use crate::{
+    input::config,
+    userdata::{self, dynamic::ComboEntry, keymap::Keymap},
+};
+
+/// Combos resolved against base layer buttons
+pub struct Combos {
+    resolver: ComboResolver,
+    tap_ms: u16,
+    /// Whether combos with the output keycode are resolved
+    output_filter: fn(Keycode) -> bool,
+}
+
+impl Combos {
+    pub fn new() -> Self {
+        Self::with_outputs(|_| true)
+    }
+
+    /// Resolve only combos whose output passes `filter`.
+    /// Other combos are disabled and never hold back their buttons.
+    pub fn with_outputs(filter: fn(Keycode) -> bool) -> Self {
+        Self {
+            resolver: ComboResolver::new(),
+            tap_ms: config::tap_duration_ms(),
+            output_filter: filter,
+        }
+    }
+
+    /// Buttons are held back or tapping, time must keep advancing
+    pub fn pending(&self) -> bool {
+        self.resolver.pending()
+    }
+
+    /// Resolve combos of pressed button `mask`.
+    /// Returns buttons passed through, and each combo output with pressed state.
+    pub fn update(
+        &mut self,
+        base: &Keymap,
+        mask: u16,
+        elapsed_ms: u16,
+    ) -> (u32, impl Iterator<Item = (Keycode, bool)>) {
+        let (combo_term_ms, entries) =
+            userdata::get(|data| (data.combo_term_ms, data.dynamic.combo));
+
+        let masks = entries.map(|entry| {
+            if (self.output_filter)(entry.output) {
+                combo_mask(base, &entry)
+            } else {
+                0
+            }
+        });
+        let output =
+            self.resolver
+                .update(mask.into(), elapsed_ms, &masks, combo_term_ms, self.tap_ms);
+
+        (
+            output.buttons,
+            entries
+                .into_iter()
+                .enumerate()
+                .map(move |(i, entry)| (entry.output, output.combos & (1 << i) != 0)),
+        )
+    }
+}
+
+/// Button mask of combo.
+/// Input keycodes are matched against base layer buttons, and unmatched combos are disabled.
+fn combo_mask(keymap: &Keymap, entry: &ComboEntry) -> u32 {
+    if entry.output == Keycode::KC_NO {
+        return 0;
+    }
+
+    let buttons = keymap.buttons();
+    let mut mask = 0;
+    for input in entry.input.iter().filter(|code| **code != Keycode::KC_NO) {
+        let matched = buttons
+            .iter()
+            .enumerate()
+            .filter(|(_, code)| *code == input)
+            .fold(0, |mask, (i, _)| mask | (1 << i));
+        if matched == 0 {
+            return 0;
+        }
+        mask |= matched;
+    }
+    mask
+}
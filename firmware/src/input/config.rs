@@ -1,57 +1,87 @@
 use embassy_rp::{
     Peri,
-    adc::{self, Channel},
-    gpio::{Input, Pin, Pull},
-    peripherals::*,
+    adc::Channel,
+    gpio::{AnyPin, Input, Level, Pull},
 };
+use embassy_time::Duration;
 
-use crate::input::reader::button::{Button, Buttons};
-pub struct InputPinout<'a> {
-    pub button1: Peri<'a, PIN_0>,
-    pub button2: Peri<'a, PIN_1>,
-    pub button3: Peri<'a, PIN_2>,
-    pub button4: Peri<'a, PIN_3>,
-
-    pub fx1: Peri<'a, PIN_4>,
-    pub fx2: Peri<'a, PIN_5>,
+use crate::{
+    input::reader::button::{BUTTON_COUNT, Button, Buttons},
+    usb,
+};
 
-    pub start: Peri<'a, PIN_6>,
+/// Button pin of board
+pub struct ButtonPin<'a> {
+    pub pin: Peri<'a, AnyPin>,
+    /// Level of pressed button
+    pub active: Level,
+    pub pull: Pull,
+}
 
-    pub left_knob: Peri<'a, PIN_26>,
-    pub right_knob: Peri<'a, PIN_27>,
+/// Input pins of board, built by [`board::input_pinout`](crate::board::input_pinout)
+pub struct InputPinout<'a> {
+    /// Buttons in [`ButtonId`](crate::input::reader::button::ButtonId) order. Optional ones may be `None`
+    pub buttons: [Option<ButtonPin<'a>>; BUTTON_COUNT],
+    /// Left and right knob ADC channels
+    pub knobs: [Channel<'a>; 2],
 }
 
 impl<'a> InputPinout<'a> {
     pub fn inputs(self) -> (Buttons<'a>, [Channel<'a>; 2]) {
         (
-            Buttons {
-                button1: button(self.button1),
-                button2: button(self.button2),
-                button3: button(self.button3),
-                button4: button(self.button4),
-                fx1: button(self.fx1),
-                fx2: button(self.fx2),
-                start: button(self.start),
-            },
-            [
-                adc::Channel::new_pin(self.left_knob, Pull::None),
-                adc::Channel::new_pin(self.right_knob, Pull::None),
-            ],
+            Buttons(self.buttons.map(|pin| Button::new(pin.map(input)))),
+            self.knobs,
         )
     }
 }
 
 #[inline(always)]
-fn button<'a>(pin: Peri<'a, impl Pin>) -> Button<'a> {
-    let mut input = Input::new(pin, Pull::Up);
+fn input(pin: ButtonPin<'_>) -> Input<'_> {
+    let mut input = Input::new(pin.pin, pin.pull);
     input.set_schmitt(true);
-    input.set_inversion(true);
-    Button::new(input)
+    // Read pressed as high
+    input.set_inversion(pin.active == Level::Low);
+    input
 }
 
 pub const KNOB_SAMPLES: usize = 32;
+/// Knob sampling cadence, independent from button edges
+pub const KNOB_SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+/// Input update cadence while any button is held or a knob is turning
+pub const INPUT_ACTIVE_TICK: Duration = Duration::from_millis(1);
+/// Fallback re-read while idle, covering edges that happen while interrupts are re-armed
+pub const INPUT_IDLE_RESYNC: Duration = Duration::from_millis(10);
 pub const MOUSE_CURSOR_SPEED: i8 = 3;
 pub const MOUSE_WHEEL_SPEED: i8 = 1;
 
 pub type KnobFilter = filter::KnobFilter<32, 10>;
-pub type ButtonDebouncer = filter::ButtonDebouncer<5>;
+pub const DEBOUNCE_MS: u16 = 5;
+/// Raw state must be stable this long to end a transition for chatter measurement
+pub const CHATTER_SETTLE_MS: u16 = 10;
+
+/// Minimum tap keycode press duration of dual-role keys and combos,
+/// so games sampling inputs once per frame still see the tap.
+pub const MIN_TAP_DURATION_MS: u16 = 32;
+
+pub type ButtonDebouncer = filter::ButtonDebouncer<DEBOUNCE_MS>;
+pub type ChatterMeter = filter::ChatterMeter<CHATTER_SETTLE_MS>;
+
+/// Tap keycode press duration of dual-role keys and combos.
+/// Twice the longest polling interval of input interfaces, so the tap outlasts a report write
+/// already in flight and is polled by the host before the release replaces it.
+/// Intervals are read from userdata like USB configuration, so call at startup.
+pub fn tap_duration_ms() -> u16 {
+    let longest = [
+        usb::config::poll_ms(|poll| poll.eac),
+        usb::config::poll_ms(|poll| poll.gamepad),
+        usb::config::poll_ms(|poll| poll.keyboard),
+        usb::config::poll_ms(|poll| poll.mouse),
+        usb::config::poll_ms(|poll| poll.switch),
+        usb::config::poll_ms(|poll| poll.xinput),
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(1);
+
+    (u16::from(longest) * 2).max(MIN_TAP_DURATION_MS)
+}
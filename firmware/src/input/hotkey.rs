@@ -0,0 +1,95 @@
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    input::reader::button::{ButtonId, ButtonInputRead},
+    userdata::{self, USB_MODE_COUNT, UsbMode},
+};
+
+/// Button bits of [`ButtonInputRead::mask`]
+pub struct HotkeyButton;
+impl HotkeyButton {
+    pub const BT_A: u16 = 1 << ButtonId::BUTTON1;
+    pub const BT_B: u16 = 1 << ButtonId::BUTTON2;
+    pub const BT_C: u16 = 1 << ButtonId::BUTTON3;
+    pub const BT_D: u16 = 1 << ButtonId::BUTTON4;
+    pub const FX_L: u16 = 1 << ButtonId::FX1;
+    pub const FX_R: u16 = 1 << ButtonId::FX2;
+    pub const START: u16 = 1 << ButtonId::START;
+}
+
+/// Default hotkeys held while plugging in to switch USB mode, in [`UsbMode::ALL`] order.
+/// Stored in userdata and editable from the console.
+pub const DEFAULT_MODE_HOTKEYS: [u16; USB_MODE_COUNT] = [
+    HotkeyButton::START | HotkeyButton::BT_B,
+    HotkeyButton::START | HotkeyButton::BT_A,
+    HotkeyButton::START | HotkeyButton::BT_C,
+    HotkeyButton::START | HotkeyButton::BT_D,
+    HotkeyButton::START | HotkeyButton::FX_L,
+];
+
+/// Hotkey held while plugging in to run LED diagnostics.
+/// Held again during diagnostics to stop them.
+pub const DIAGNOSTICS_HOTKEY: u16 = HotkeyButton::START | HotkeyButton::FX_L | HotkeyButton::FX_R;
+/// Duration [`DIAGNOSTICS_HOTKEY`] must be held to stop diagnostics
+pub const DIAGNOSTICS_STOP_HOLD: Duration = Duration::from_secs(3);
+
+/// Hotkey held during operation to return to HID mode
+pub const RETURN_HID_HOTKEY: u16 = HotkeyButton::START | HotkeyButton::BT_A | HotkeyButton::BT_D;
+/// Duration [`RETURN_HID_HOTKEY`] must be held
+pub const RETURN_HID_HOLD: Duration = Duration::from_secs(3);
+
+/// Find USB mode for boot hotkey
+pub fn mode_hotkey(read: &ButtonInputRead) -> Option<UsbMode> {
+    let mask = read.mask();
+    let hotkeys = userdata::get(|data| data.mode_hotkeys);
+    UsbMode::ALL
+        .into_iter()
+        .zip(hotkeys)
+        .find(|(_, hotkey)| *hotkey != 0 && *hotkey == mask)
+        .map(|(mode, _)| mode)
+}
+
+/// Detects hotkey held for a duration
+pub struct HoldHotkey {
+    hotkey: u16,
+    duration: Duration,
+    since: Option<Instant>,
+    triggered: bool,
+}
+
+impl HoldHotkey {
+    pub const fn new(hotkey: u16, duration: Duration) -> Self {
+        Self {
+            hotkey,
+            duration,
+            since: None,
+            triggered: false,
+        }
+    }
+
+    /// Ignore hotkey held since before creation until it is released
+    pub const fn after_release(mut self) -> Self {
+        self.triggered = true;
+        self
+    }
+
+    /// Returns `true` once when hotkey is held for the duration
+    pub fn update(&mut self, read: &ButtonInputRead, now: Instant) -> bool {
+        if read.mask() != self.hotkey {
+            self.since = None;
+            self.triggered = false;
+            return false;
+        }
+
+        // Wait for release before triggering again
+        if self.triggered {
+            return false;
+        }
+
+        let since = *self.since.get_or_insert(now);
+        if now.duration_since(since) >= self.duration {
+            self.triggered = true;
+        }
+        self.triggered
+    }
+}
@@ -6,8 +6,65 @@ use crate::{
         report,
     },
     keycodes,
+    usb::hid::GamepadInputReport,
 };
 
+/// Hold role of a dual-role keycode
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HoldRole {
+    /// HID modifier bits
+    Mods(u8),
+    Layer(u8),
+}
+
+/// Split `QK_MOD_TAP` and `QK_LAYER_TAP` keycode into tap keycode and hold role.
+/// The tap keycode is the low 8 bits, so only basic keycodes can be tapped
+pub fn dual_role(code: Keycode) -> Option<(Keycode, HoldRole)> {
+    let tap = Keycode(code.0 & 0xFF);
+    match code.0 {
+        Keycode::RANGE_QK_MOD_TAP_START..=Keycode::RANGE_QK_MOD_TAP_END => {
+            Some((tap, HoldRole::Mods(hid_mods((code.0 >> 8) as u8))))
+        }
+
+        Keycode::RANGE_QK_LAYER_TAP_START..=Keycode::RANGE_QK_LAYER_TAP_END => {
+            Some((tap, HoldRole::Layer(((code.0 >> 8) & 0x0F) as u8)))
+        }
+
+        _ => None,
+    }
+}
+
+/// Entry index of `QK_TAP_DANCE` keycode
+pub fn tap_dance_index(code: Keycode) -> Option<usize> {
+    match code.0 {
+        Keycode::RANGE_QK_TAP_DANCE_START..=Keycode::RANGE_QK_TAP_DANCE_END => {
+            Some((code.0 & 0xFF) as usize)
+        }
+
+        _ => None,
+    }
+}
+
+/// Layer of `QK_MOMENTARY` keycode
+pub fn momentary_layer(code: Keycode) -> Option<u8> {
+    match code.0 {
+        Keycode::RANGE_QK_MOMENTARY_START..=Keycode::RANGE_QK_MOMENTARY_END => {
+            Some((code.0 & 0x1F) as u8)
+        }
+
+        _ => None,
+    }
+}
+
+/// Convert QMK 5 bits mods (ctrl, shift, alt, gui, right hand flag) to HID modifier bits
+pub fn hid_mods(mods: u8) -> u8 {
+    if mods & 0x10 != 0 {
+        (mods & 0x0F) << 4
+    } else {
+        mods & 0x0F
+    }
+}
+
 #[derive(Default)]
 pub struct InputReports {
     gamepad: Option<GamepadInputBuilder>,
@@ -16,6 +73,11 @@ pub struct InputReports {
 }
 
 impl InputReports {
+    /// Take gamepad report only
+    pub fn into_gamepad(self) -> GamepadInputReport {
+        self.gamepad.unwrap_or_default().build()
+    }
+
     pub fn send(self) {
         if let Some(gamepad) = self.gamepad {
             report::GAMEPAD.signal(gamepad.build());
@@ -38,17 +100,41 @@ impl InputReports {
         const MOUSE_KEY_END: u16 = Keycode::QK_MOUSE_ACCELERATION_2.0;
 
         match code.0 {
-            Keycode::RANGE_QK_BASIC_START..MOUSE_KEY_START => {
+            MOUSE_KEY_START..=MOUSE_KEY_END => self.mouse(code, pressed),
+            Keycode::RANGE_QK_BASIC_START..=Keycode::RANGE_QK_BASIC_END => {
                 self.keyboard(code, pressed);
             }
-            MOUSE_KEY_START..=MOUSE_KEY_END => self.mouse(code, pressed),
+
+            // Basic keycode with modifiers, e.g. `LCTL(KC_R)` or `LCTL(MS_BTN1)`
+            Keycode::RANGE_QK_MODS_START..=Keycode::RANGE_QK_MODS_END => {
+                let keyboard = self.keyboard.get_or_insert_default();
+                if pressed {
+                    keyboard.modifiers(hid_mods((code.0 >> 8) as u8));
+                }
+                self.key(Keycode(code.0 & 0xFF), pressed);
+            }
 
             GAMEPAD_KEY_START..=GAMEPAD_KEY_END => self.gamepad(code, pressed),
 
+            // Dual-role keys resolved elsewhere act as their tap keycode here
+            Keycode::RANGE_QK_MOD_TAP_START..=Keycode::RANGE_QK_LAYER_TAP_END => {
+                self.key(Keycode(code.0 & 0xFF), pressed);
+            }
+
             _ => {}
         }
     }
 
+    /// Press HID modifier bits
+    pub fn modifiers(&mut self, bits: u8) {
+        self.keyboard.get_or_insert_default().modifiers(bits);
+    }
+
+    /// Keyboard report being built, if any key mapped to it
+    pub fn keyboard_report(&mut self) -> Option<&mut KeyboardInputBuilder> {
+        self.keyboard.as_mut()
+    }
+
     #[inline(always)]
     fn gamepad(&mut self, code: Keycode, pressed: bool) {
         const BUTTON_START: u16 = keycodes::JOY_BTN1.0;
@@ -0,0 +1,138 @@
+//! Input latency measurement.
+//! Raw edges are timestamped when the GPIO interrupt wakes the input loop.
+//! A debounced transition is claimed by the report carrying the change,
+//! and completed when that report is written.
+
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::Instant;
+use filter::{Histogram, HistogramSummary};
+
+/// 64 buckets of 125us, up to 8ms
+type LatencyHistogram = Histogram<64, 125>;
+
+/// Measured stages in microseconds
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum LatencyStage {
+    /// Raw edge to debounced transition
+    Debounce = 0,
+    /// Debounced transition to report written
+    Report = 1,
+    /// Raw edge to report written
+    Total = 2,
+}
+
+impl LatencyStage {
+    pub const ALL: [(&str, LatencyStage); 3] = [
+        ("debounce", LatencyStage::Debounce),
+        ("report", LatencyStage::Report),
+        ("total", LatencyStage::Total),
+    ];
+}
+
+/// Debounced button transition
+#[derive(Clone, Copy)]
+pub struct Transition {
+    edge: Instant,
+    debounced: Instant,
+}
+
+struct Latency {
+    histograms: [LatencyHistogram; 3],
+    /// First transition of current input read not claimed by a report yet
+    unclaimed: Option<Transition>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LATENCY: Mutex<CriticalSectionRawMutex, RefCell<Latency>> =
+    Mutex::new(RefCell::new(Latency {
+        histograms: [const { LatencyHistogram::new() }; 3],
+        unclaimed: None,
+    }));
+
+/// Start or stop measurement
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    LATENCY.lock(|latency| latency.borrow_mut().unclaimed = None);
+}
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn summary(stage: LatencyStage) -> HistogramSummary {
+    LATENCY.lock(|latency| latency.borrow().histograms[stage as usize].summary())
+}
+
+pub fn reset() {
+    LATENCY.lock(|latency| {
+        let mut latency = latency.borrow_mut();
+        for histogram in &mut latency.histograms {
+            histogram.reset();
+        }
+        latency.unclaimed = None;
+    });
+}
+
+/// Record debounced transition of a button with raw edge time
+pub fn debounced(edge: Instant) {
+    if !enabled() {
+        return;
+    }
+
+    let now = Instant::now();
+    LATENCY.lock(|latency| {
+        let mut latency = latency.borrow_mut();
+        latency.histograms[LatencyStage::Debounce as usize].record(micros(edge, now));
+        latency.unclaimed.get_or_insert(Transition {
+            edge,
+            debounced: now,
+        });
+    });
+}
+
+/// Take transition of current input read for a report carrying the change
+pub fn claim() -> Option<Transition> {
+    if !enabled() {
+        return None;
+    }
+
+    LATENCY.lock(|latency| latency.borrow_mut().unclaimed.take())
+}
+
+/// Drop transition no report carried after inputs are mapped,
+/// e.g. of a button mapped to nothing
+pub fn discard_unclaimed() {
+    if !enabled() {
+        return;
+    }
+
+    LATENCY.lock(|latency| latency.borrow_mut().unclaimed = None);
+}
+
+/// Record written report completing its transition
+pub fn written(transition: Transition) {
+    if !enabled() {
+        return;
+    }
+
+    let now = Instant::now();
+    LATENCY.lock(|latency| {
+        let mut latency = latency.borrow_mut();
+        latency.histograms[LatencyStage::Report as usize].record(micros(transition.debounced, now));
+        latency.histograms[LatencyStage::Total as usize].record(micros(transition.edge, now));
+    });
+}
+
+#[inline]
+fn micros(from: Instant, to: Instant) -> u32 {
+    to.saturating_duration_since(from)
+        .as_micros()
+        .min(u32::MAX as _) as u32
+}
@@ -0,0 +1,263 @@
+//! Layered keymap with combos, dual-role keys, tap dances and key overrides.
+//! Combos are resolved first, and buttons of an active combo are not mapped.
+//! Button keycodes are resolved on press and kept until release,
+//! so a layer change never swaps the key under a held button.
+//! Key overrides apply last, to the keyboard report of the active layer.
+
+use embassy_time::Instant;
+use filter::{TapDance, TapDanceAction, TapHold, TapHoldAction};
+use keycode::Keycode;
+
+use crate::{
+    input::{
+        InputRead, KnobTurn,
+        combo::Combos,
+        config,
+        key::{HoldRole, InputReports, dual_role, momentary_layer, tap_dance_index},
+        reader::button::BUTTON_COUNT,
+        ticker::ElapsedTimer,
+    },
+    userdata::{
+        self,
+        dynamic::{KeyOverrideEntry, TapDanceEntry},
+        keymap::{Keymap, LAYER_COUNT},
+    },
+};
+
+struct ButtonKey {
+    /// Keycode resolved on press
+    code: Option<Keycode>,
+    tap_hold: TapHold,
+    tap_dance: TapDance,
+}
+
+impl ButtonKey {
+    const DEFAULT: Self = Self {
+        code: None,
+        tap_hold: TapHold::new(),
+        tap_dance: TapDance::new(),
+    };
+
+    /// Dual-role or tap dance key is undecided or tapping
+    fn pending(&self) -> bool {
+        self.tap_hold.pending() || self.tap_dance.pending()
+    }
+}
+
+pub struct LayerState {
+    /// Buttons in [`ButtonId`](super::reader::button::ButtonId) order
+    buttons: [ButtonKey; BUTTON_COUNT],
+    combos: Combos,
+    /// Highest layer of held layer keys
+    layer: u8,
+    /// Tap keycode press duration
+    tap_ms: u16,
+    timer: ElapsedTimer,
+}
+
+impl LayerState {
+    pub fn new() -> Self {
+        Self {
+            buttons: [ButtonKey::DEFAULT; BUTTON_COUNT],
+            combos: Combos::new(),
+            layer: 0,
+            tap_ms: config::tap_duration_ms(),
+            timer: ElapsedTimer::new(Instant::now()),
+        }
+    }
+
+    /// Combos, dual-role or tap dance keys are undecided or tapping, time must keep advancing
+    pub fn pending(&self) -> bool {
+        self.combos.pending() || self.buttons.iter().any(ButtonKey::pending)
+    }
+
+    /// Map inputs to reports using keymap of active layer
+    pub fn map(&mut self, keymaps: &[Keymap; LAYER_COUNT], input: InputRead) -> InputReports {
+        let elapsed_ms = self.timer.next_elapsed_ms();
+        let (tapping_term_ms, tap_dances, key_overrides) = userdata::get(|data| {
+            (
+                data.tapping_term_ms,
+                data.dynamic.tap_dance,
+                data.dynamic.key_override,
+            )
+        });
+
+        let mut reports = InputReports::default();
+        let (mask, combos) = self
+            .combos
+            .update(&keymaps[0], input.buttons.mask(), elapsed_ms);
+        for (code, pressed) in combos {
+            reports.key(code, pressed);
+        }
+
+        let mut layer = 0;
+        for (i, key) in self.buttons.iter_mut().enumerate() {
+            let pressed = mask & (1 << i) != 0;
+            let code = key
+                .code
+                .unwrap_or_else(|| resolve(keymaps, self.layer, |keymap| keymap.buttons()[i]));
+            if pressed {
+                key.code = Some(code);
+            }
+
+            if let Some(index) = tap_dance_index(code) {
+                let entry = tap_dances.get(index).unwrap_or(&TapDanceEntry::DEFAULT);
+                let term_ms = match entry.tapping_term {
+                    0 => tapping_term_ms,
+                    term => term,
+                };
+                let action = key
+                    .tap_dance
+                    .update(pressed, elapsed_ms, term_ms, self.tap_ms);
+
+                // Release all actions, so their reports are sent
+                for code in entry.keycodes() {
+                    reports.key(code, false);
+                }
+                if let Some(code) = tap_dance_keycode(entry, action) {
+                    match momentary_layer(code) {
+                        Some(momentary) => layer = layer.max(momentary),
+                        None => reports.key(code, true),
+                    }
+                }
+
+                // Keep tap dance keycode until the dance ends
+                if !pressed && !key.tap_dance.pending() {
+                    key.code = None;
+                }
+                continue;
+            }
+
+            let Some((tap, hold)) = dual_role(code) else {
+                if pressed && let Some(momentary) = momentary_layer(code) {
+                    layer = layer.max(momentary);
+                }
+
+                reports.key(code, pressed);
+                if !pressed {
+                    key.code = None;
+                }
+                continue;
+            };
+
+            match key
+                .tap_hold
+                .update(pressed, elapsed_ms, tapping_term_ms, self.tap_ms)
+            {
+                TapHoldAction::Tap => reports.key(tap, true),
+                TapHoldAction::Hold => match hold {
+                    HoldRole::Mods(mods) => reports.modifiers(mods),
+                    HoldRole::Layer(hold_layer) => layer = layer.max(hold_layer),
+                },
+                TapHoldAction::None => reports.key(tap, false),
+            }
+
+            // Keep tapping keycode until the tap is sent
+            if !pressed && !key.tap_hold.pending() {
+                key.code = None;
+            }
+        }
+        self.layer = layer;
+
+        for (delta, left, right) in [
+            (
+                input.knobs.0.delta,
+                resolve(keymaps, layer, |keymap| keymap.left_knob_left),
+                resolve(keymaps, layer, |keymap| keymap.left_knob_right),
+            ),
+            (
+                input.knobs.1.delta,
+                resolve(keymaps, layer, |keymap| keymap.right_knob_left),
+                resolve(keymaps, layer, |keymap| keymap.right_knob_right),
+            ),
+        ] {
+            let turn = KnobTurn::from(delta);
+            reports.key(left, turn == KnobTurn::Left);
+            reports.key(right, turn == KnobTurn::Right);
+        }
+
+        apply_key_overrides(&mut reports, &key_overrides, layer);
+        reports
+    }
+}
+
+/// Keycode of tap dance action. Unset actions fall back like Vial:
+/// hold to tap, double tap to tap, and tap-hold to double tap
+fn tap_dance_keycode(entry: &TapDanceEntry, action: TapDanceAction) -> Option<Keycode> {
+    let or = |code: Keycode, fallback: Keycode| {
+        if code == Keycode::KC_NO {
+            fallback
+        } else {
+            code
+        }
+    };
+
+    let double_tap = or(entry.on_double_tap, entry.on_tap);
+    match action {
+        TapDanceAction::None => None,
+        TapDanceAction::Tap => Some(entry.on_tap),
+        TapDanceAction::Hold => Some(or(entry.on_hold, entry.on_tap)),
+        TapDanceAction::DoubleTap => Some(double_tap),
+        TapDanceAction::TapHold => Some(or(entry.on_tap_hold, double_tap)),
+    }
+}
+
+/// Replace trigger keys of enabled key overrides matching held modifiers on `layer`.
+/// Overrides are matched against the mapped report, so a replacement never triggers another.
+fn apply_key_overrides<const N: usize>(
+    reports: &mut InputReports,
+    entries: &[KeyOverrideEntry; N],
+    layer: u8,
+) {
+    let Some(keyboard) = reports.keyboard_report() else {
+        return;
+    };
+
+    let mods = keyboard.modifier_bits();
+    let mut matched = [None; N];
+    for (slot, entry) in matched.iter_mut().zip(entries) {
+        let active = entry.enabled()
+            && entry.layers & (1 << layer) != 0
+            && entry.mods_match(mods)
+            && match trigger_usage(entry.trigger) {
+                Some(usage) => keyboard.pressed(usage),
+                None => entry.trigger == Keycode::KC_NO,
+            };
+        if active {
+            *slot = Some(entry);
+        }
+    }
+
+    for entry in matched.into_iter().flatten() {
+        if let Some(keyboard) = reports.keyboard_report() {
+            if let Some(usage) = trigger_usage(entry.trigger) {
+                keyboard.release(usage);
+            }
+            keyboard.release_modifiers(entry.suppressed_mods);
+        }
+        reports.key(entry.replacement, true);
+    }
+}
+
+/// HID usage of key override trigger. Only basic keycodes other than modifiers can trigger
+fn trigger_usage(code: Keycode) -> Option<u8> {
+    const SCAN_CODE_START: u16 = Keycode::KC_A.0;
+    const SCAN_CODE_END: u16 = Keycode::KC_EXSEL.0;
+
+    matches!(code.0, SCAN_CODE_START..=SCAN_CODE_END).then_some(code.0 as u8)
+}
+
+/// Keycode on `layer`, falling through transparent keys to lower layers
+fn resolve(
+    keymaps: &[Keymap; LAYER_COUNT],
+    layer: u8,
+    key: impl Fn(&Keymap) -> Keycode,
+) -> Keycode {
+    keymaps
+        .iter()
+        .take(layer as usize + 1)
+        .rev()
+        .map(key)
+        .find(|code| *code != Keycode::KC_TRANSPARENT)
+        .unwrap_or(Keycode::KC_NO)
+}
@@ -16,4 +16,11 @@ impl InputRead {
         knobs: (KnobValue::DEFAULT, KnobValue::DEFAULT),
         buttons: ButtonInputRead::DEFAULT,
     };
+
+    /// Whether any button is held or a knob is turning
+    pub fn active(&self) -> bool {
+        self.buttons != ButtonInputRead::DEFAULT
+            || self.knobs.0.delta != 0
+            || self.knobs.1.delta != 0
+    }
 }
@@ -1,6 +1,14 @@
+use core::future::pending;
+
+use embassy_futures::select::select_array;
 use embassy_rp::gpio::{Input, Level};
+use embassy_time::Instant;
 
-use crate::input::config::ButtonDebouncer;
+use crate::input::{
+    chatter,
+    config::{ButtonDebouncer, ChatterMeter},
+    latency,
+};
 
 pub struct ButtonInputReader<'a> {
     inputs: Buttons<'a>,
@@ -12,51 +20,74 @@ impl<'a> ButtonInputReader<'a> {
     }
 
     pub fn read(&mut self, elapsed_ms: u16) -> ButtonInputRead {
-        let button1 = self.inputs.button1.read(elapsed_ms);
-        let button2 = self.inputs.button2.read(elapsed_ms);
-        let button3 = self.inputs.button3.read(elapsed_ms);
-        let button4 = self.inputs.button4.read(elapsed_ms);
-
-        let fx1 = self.inputs.fx1.read(elapsed_ms);
-        let fx2 = self.inputs.fx2.read(elapsed_ms);
-
-        let start = self.inputs.start.read(elapsed_ms);
-
-        ButtonInputRead {
-            button1,
-            button2,
-            button3,
-            button4,
-            fx1,
-            fx2,
-            start,
-        }
+        let buttons = &mut self.inputs.0;
+        ButtonInputRead(core::array::from_fn(|i| buttons[i].read(i, elapsed_ms)))
+    }
+
+    /// Wait for an edge on any button using GPIO interrupts.
+    /// The edge time is captured as soon as the interrupt wakes the task.
+    pub async fn wait_for_edge(&mut self) {
+        let ((), index) = select_array(self.inputs.0.each_mut().map(|button| async {
+            match &mut button.input {
+                Some(input) => input.wait_for_any_edge().await,
+                // Not on board
+                None => pending().await,
+            }
+        }))
+        .await;
+        let now = Instant::now();
+
+        self.inputs.0[index].captured = Some(now);
     }
 }
 
+/// Main controller buttons
+pub const MAIN_BUTTON_COUNT: usize = 7;
+/// Auxiliary buttons on spare GPIOs, e.g. Escape, Service, Test or Coin.
+/// Unconnected ones stay released with the pull-up.
+pub const AUX_BUTTON_COUNT: usize = 4;
+pub const BUTTON_COUNT: usize = MAIN_BUTTON_COUNT + AUX_BUTTON_COUNT;
+
+/// Indices of buttons in [`Buttons`] and [`ButtonInputRead`], also bits of [`ButtonInputRead::mask`]
+pub struct ButtonId;
+impl ButtonId {
+    pub const BUTTON1: usize = 0;
+    pub const BUTTON2: usize = 1;
+    pub const BUTTON3: usize = 2;
+    pub const BUTTON4: usize = 3;
+    pub const FX1: usize = 4;
+    pub const FX2: usize = 5;
+    pub const START: usize = 6;
+    pub const AUX1: usize = 7;
+    pub const AUX2: usize = 8;
+    pub const AUX3: usize = 9;
+    pub const AUX4: usize = 10;
+}
+
+/// Button levels in [`ButtonId`] order
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub struct ButtonInputRead {
-    pub button1: Level,
-    pub button2: Level,
-    pub button3: Level,
-    pub button4: Level,
+pub struct ButtonInputRead(pub [Level; BUTTON_COUNT]);
 
-    pub fx1: Level,
-    pub fx2: Level,
+impl ButtonInputRead {
+    pub const DEFAULT: Self = Self([Level::Low; BUTTON_COUNT]);
 
-    pub start: Level,
-}
+    #[inline]
+    pub const fn level(&self, button: usize) -> Level {
+        self.0[button]
+    }
 
-impl ButtonInputRead {
-    pub const DEFAULT: Self = Self {
-        button1: Level::Low,
-        button2: Level::Low,
-        button3: Level::Low,
-        button4: Level::Low,
-        fx1: Level::Low,
-        fx2: Level::Low,
-        start: Level::Low,
-    };
+    #[inline]
+    pub fn pressed(&self, button: usize) -> bool {
+        self.0[button] == Level::High
+    }
+
+    /// Pressed buttons as bit mask in [`ButtonId`] order
+    pub fn mask(&self) -> u16 {
+        self.0
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (i, level)| mask | ((*level as u16) << i))
+    }
 }
 
 impl Default for ButtonInputRead {
@@ -65,32 +96,75 @@ impl Default for ButtonInputRead {
     }
 }
 
-pub struct Buttons<'a> {
-    pub button1: Button<'a>,
-    pub button2: Button<'a>,
-    pub button3: Button<'a>,
-    pub button4: Button<'a>,
-
-    pub fx1: Button<'a>,
-    pub fx2: Button<'a>,
-
-    pub start: Button<'a>,
+/// Buttons in [`ButtonId`] order
+pub struct Buttons<'a>(pub [Button<'a>; BUTTON_COUNT]);
+
+impl<'a> Buttons<'a> {
+    /// Read current levels without debouncing
+    pub fn read_raw(&self) -> ButtonInputRead {
+        ButtonInputRead(self.0.each_ref().map(|button| {
+            button
+                .input
+                .as_ref()
+                .map_or(Level::Low, |input| input.get_level())
+        }))
+    }
 }
 
 pub struct Button<'a> {
-    pub input: Input<'a>,
+    /// `None` if the button is not on board
+    pub input: Option<Input<'a>>,
     debouncer: ButtonDebouncer,
+    chatter: ChatterMeter,
+
+    /// Last raw and debounced state for latency measurement
+    raw: bool,
+    debounced: bool,
+    /// Time of last raw edge
+    edge: Instant,
+    /// Edge time captured by GPIO interrupt, not yet consumed by [`Button::read`]
+    captured: Option<Instant>,
 }
 
 impl<'a> Button<'a> {
-    pub const fn new(input: Input<'a>) -> Self {
+    pub const fn new(input: Option<Input<'a>>) -> Self {
         Self {
             input,
             debouncer: ButtonDebouncer::new(false),
+            chatter: ChatterMeter::new(false),
+
+            raw: false,
+            debounced: false,
+            edge: Instant::MIN,
+            captured: None,
         }
     }
 
-    fn read(&mut self, elapsed_ms: u16) -> Level {
-        Level::from(self.debouncer.debounce(self.input.is_high(), elapsed_ms))
+    /// Read debounced level of button at `index` in [`Buttons`]
+    fn read(&mut self, index: usize, elapsed_ms: u16) -> Level {
+        let raw = self.input.as_ref().is_some_and(|input| input.is_high());
+        let debounced = self.debouncer.debounce(raw, elapsed_ms);
+
+        if let Some(bounce) = self.chatter.update(raw, elapsed_ms) {
+            chatter::bounced(index, bounce);
+        }
+        if self.debounced && !debounced {
+            chatter::released(index);
+        }
+        let captured = self.captured.take();
+
+        if latency::enabled() {
+            if raw != self.raw {
+                self.edge = captured.unwrap_or_else(Instant::now);
+            }
+
+            if debounced != self.debounced {
+                latency::debounced(self.edge);
+            }
+        }
+        self.raw = raw;
+        self.debounced = debounced;
+
+        Level::from(debounced)
     }
 }
@@ -1,14 +1,205 @@
-use crate::usb::{self, Driver, eac::EacInputReport, hid::GamepadInputReport};
+use crate::{
+    input::latency::{self, Transition},
+    log,
+    usb::{
+        self, Driver,
+        eac::EacInputReport,
+        hid::GamepadInputReport,
+        midi::MidiPacket,
+        switch::SwitchInputReport,
+        xinput::{XInputEndpointIn, XInputEndpointOut, XInputReport, xinput_interface},
+    },
+    watchdog::{self, Heartbeat, UsbWrite},
+};
+use core::cell::{Cell, RefCell};
+
 use embassy_executor::SpawnToken;
-use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, signal::Signal};
-use embassy_usb::class::hid::{self, HidWriter};
+use embassy_futures::join::join;
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::ThreadModeRawMutex},
+    channel::Channel,
+    signal::Signal,
+};
+use embassy_usb::{
+    class::{
+        hid::{self, HidWriter},
+        midi::{self, MidiClass},
+    },
+    driver::{Endpoint, EndpointIn, EndpointOut},
+};
+use filter::ReportDedup;
+use portable_atomic::{AtomicU32, Ordering};
 use static_cell::StaticCell;
 use usbd_hid::descriptor::{AsInputReport, KeyboardReport, MouseReport};
+use zerocopy::IntoBytes;
+
+/// Counters of an input report interface
+pub struct ReportStats {
+    /// Reports written to host
+    sent: AtomicU32,
+    /// Reports overwritten before written
+    dropped: AtomicU32,
+    /// Failed writes
+    errors: AtomicU32,
+}
+
+/// Snapshot of [`ReportStats`]
+#[derive(Clone, Copy)]
+pub struct ReportCounts {
+    pub sent: u32,
+    pub dropped: u32,
+    pub errors: u32,
+}
+
+impl ReportStats {
+    pub const fn new() -> Self {
+        Self {
+            sent: AtomicU32::new(0),
+            dropped: AtomicU32::new(0),
+            errors: AtomicU32::new(0),
+        }
+    }
+
+    pub fn counts(&self) -> ReportCounts {
+        ReportCounts {
+            sent: self.sent.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.sent.store(0, Ordering::Relaxed);
+        self.dropped.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn add_sent(&self, count: u32) {
+        self.sent.fetch_add(count, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn add_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Input report written through [`ReportSignal`]
+pub trait Report: PartialEq + Clone {
+    /// Returns `true` if report contains relative values, which must be written even if unchanged.
+    fn relative(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if buttons equal ones of `other`.
+    /// Reports with changed buttons carry debounced transitions for latency measurement.
+    fn same_buttons(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Report for EacInputReport {}
+impl Report for GamepadInputReport {}
+impl Report for KeyboardReport {}
+impl Report for SwitchInputReport {}
+impl Report for XInputReport {}
+
+impl Report for MouseReport {
+    fn relative(&self) -> bool {
+        self.x != 0 || self.y != 0 || self.wheel != 0 || self.pan != 0
+    }
+
+    fn same_buttons(&self, other: &Self) -> bool {
+        self.buttons == other.buttons
+    }
+}
+
+/// [`Signal`] holding latest report.
+/// Skips reports already written and counts overwritten reports.
+pub struct ReportSignal<T> {
+    signal: Signal<ThreadModeRawMutex, T>,
+    dedup: Mutex<ThreadModeRawMutex, RefCell<ReportDedup<T>>>,
+    /// Transition carried by signaled report, completed when it is written
+    transition: Mutex<ThreadModeRawMutex, Cell<Option<Transition>>>,
+    pub stats: ReportStats,
+}
+
+impl<T: Report> ReportSignal<T> {
+    pub const fn new() -> Self {
+        Self {
+            signal: Signal::new(),
+            dedup: Mutex::new(RefCell::new(ReportDedup::new())),
+            transition: Mutex::new(Cell::new(None)),
+            stats: ReportStats::new(),
+        }
+    }
+
+    pub fn signal(&self, report: T) {
+        let Some(changed) = self.dedup.lock(|dedup| {
+            let mut dedup = dedup.borrow_mut();
+            let changed = dedup.last().is_none_or(|last| !last.same_buttons(&report));
+            dedup.check(&report, report.relative()).then_some(changed)
+        }) else {
+            return;
+        };
+
+        // Keep earlier transition of overwritten report
+        if changed
+            && self.transition.lock(|cell| cell.get().is_none())
+            && let Some(transition) = latency::claim()
+        {
+            self.transition.lock(|cell| cell.set(Some(transition)));
+        }
+
+        if self.signal.signaled() {
+            self.stats.add_dropped();
+        }
+        self.signal.signal(report);
+    }
+
+    /// Wait for report with its transition
+    async fn wait(&self) -> (T, Option<Transition>) {
+        let report = self.signal.wait().await;
+        (report, self.transition.lock(|cell| cell.take()))
+    }
+
+    /// Count written report and complete its transition
+    fn written(&self, transition: Option<Transition>) {
+        watchdog::heartbeat(Heartbeat::Usb);
+        self.stats.add_sent(1);
+        if let Some(transition) = transition {
+            latency::written(transition);
+        }
+    }
+
+    /// Count failed write and write `report` again unless a newer report replaces it.
+    /// Inputs may stay unchanged, so the host would otherwise keep a stale report (e.g. a held key).
+    /// Transition stays pending for the next written report.
+    fn write_failed(&self, report: T, transition: Option<Transition>) {
+        self.stats.add_error();
+        if transition.is_some() {
+            self.transition.lock(|cell| cell.set(transition));
+        }
+
+        let retry = self.dedup.lock(|dedup| dedup.borrow_mut().failed(report));
+        if let Some(report) = retry
+            && !self.signal.signaled()
+        {
+            self.signal.signal(report);
+        }
+    }
+}
 
 macro_rules! define_hid_task {
     ($signal:ident, $name:ident : $ty:ty, $config:expr) => {
         // Only used within input tasks.
-        pub static $signal: Signal<ThreadModeRawMutex, $ty> = Signal::new();
+        pub static $signal: ReportSignal<$ty> = ReportSignal::new();
 
         pub fn $name(
             builder: &mut embassy_usb::Builder<'static, Driver>,
@@ -30,16 +221,26 @@ macro_rules! define_hid_task {
 }
 
 #[inline]
-async fn task<T: AsInputReport, const N: usize>(
-    rx: &Signal<ThreadModeRawMutex, T>,
+async fn task<T: AsInputReport + Report, const N: usize>(
+    rx: &ReportSignal<T>,
     mut writer: HidWriter<'static, Driver, N>,
 ) -> ! {
     writer.ready().await;
 
     loop {
-        match writer.write_serialize(&rx.wait().await).await {
-            Ok(()) => {}
-            Err(e) => defmt::error!("Failed to send input report: {:?}", e),
+        let (report, transition) = rx.wait().await;
+        let write = UsbWrite::start();
+        let result = writer.write_serialize(&report).await;
+        drop(write);
+
+        match result {
+            Ok(()) => rx.written(transition),
+            Err(e) => {
+                rx.write_failed(report, transition);
+                log::error!("Failed to send input report: {:?}", e);
+                // Retry once endpoint is usable
+                writer.ready().await;
+            }
         };
     }
 }
@@ -49,3 +250,128 @@ define_hid_task!(EAC, eac_report_task: EacInputReport, usb::config::eac());
 define_hid_task!(GAMEPAD, gamepad_report_task: GamepadInputReport, usb::config::gamepad());
 define_hid_task!(KEYBOARD, keyboard_report_task: KeyboardReport, usb::config::keyboard());
 define_hid_task!(MOUSE, mouse_report_task: MouseReport, usb::config::mouse());
+
+define_hid_task!(SWITCH, switch_report_task: SwitchInputReport, usb::config::switch());
+
+// Only used within input tasks.
+pub static XINPUT: ReportSignal<XInputReport> = ReportSignal::new();
+
+pub fn xinput_report_task(
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(mut ep_in: XInputEndpointIn, mut ep_out: XInputEndpointOut) {
+        let writer = async {
+            ep_in.wait_enabled().await;
+
+            loop {
+                let (report, transition) = XINPUT.wait().await;
+                let write = UsbWrite::start();
+                let result = ep_in.write(report.as_bytes()).await;
+                drop(write);
+
+                match result {
+                    Ok(()) => XINPUT.written(transition),
+                    Err(e) => {
+                        XINPUT.write_failed(report, transition);
+                        log::error!("Failed to send input report: {:?}", e);
+                        // Retry once endpoint is usable
+                        ep_in.wait_enabled().await;
+                    }
+                };
+            }
+        };
+
+        // Discard rumble and LED output reports
+        let reader = async {
+            let mut buf = [0_u8; 32];
+            loop {
+                if ep_out.read(&mut buf).await.is_err() {
+                    ep_out.wait_enabled().await;
+                }
+            }
+        };
+
+        join(writer, reader).await;
+    }
+
+    let (ep_in, ep_out) = xinput_interface(builder, usb::config::poll_ms(|poll| poll.xinput));
+    inner(ep_in, ep_out)
+}
+
+// Only used within input tasks.
+// Unlike reports, every MIDI event must be delivered so events are queued.
+pub static MIDI: Channel<ThreadModeRawMutex, MidiPacket, 32> = Channel::new();
+/// MIDI event counters. Events dropped on full queue are counted by sender.
+pub static MIDI_STATS: ReportStats = ReportStats::new();
+
+/// Counters of every report interface
+pub static REPORT_STATS: [(&str, &ReportStats); 7] = [
+    ("eac", &EAC.stats),
+    ("gamepad", &GAMEPAD.stats),
+    ("keyboard", &KEYBOARD.stats),
+    ("mouse", &MOUSE.stats),
+    ("switch", &SWITCH.stats),
+    ("xinput", &XINPUT.stats),
+    ("midi", &MIDI_STATS),
+];
+
+pub fn midi_report_task(
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+) -> SpawnToken<impl Sized + use<>> {
+    #[embassy_executor::task]
+    async fn inner(
+        sender: midi::Sender<'static, Driver>,
+        mut receiver: midi::Receiver<'static, Driver>,
+    ) {
+        let writer = async {
+            let mut sender = sender;
+            sender.wait_connection().await;
+
+            let mut buf = [0_u8; 64];
+            loop {
+                // Send queued events at once
+                let mut len = 0;
+                buf[..4].copy_from_slice(&MIDI.receive().await);
+                len += 4;
+                while len < buf.len() {
+                    let Ok(packet) = MIDI.try_receive() else {
+                        break;
+                    };
+                    buf[len..len + 4].copy_from_slice(&packet);
+                    len += 4;
+                }
+
+                let write = UsbWrite::start();
+                let result = sender.write_packet(&buf[..len]).await;
+                drop(write);
+
+                match result {
+                    Ok(()) => {
+                        watchdog::heartbeat(Heartbeat::Usb);
+                        MIDI_STATS.add_sent(len as u32 / 4);
+                    }
+                    Err(e) => {
+                        MIDI_STATS.add_error();
+                        log::error!("Failed to send MIDI events: {:?}", e);
+                    }
+                };
+            }
+        };
+
+        // Discard incoming MIDI events
+        let reader = async {
+            let mut buf = [0_u8; 64];
+            loop {
+                if receiver.read_packet(&mut buf).await.is_err() {
+                    receiver.wait_connection().await;
+                }
+            }
+        };
+
+        join(writer, reader).await;
+    }
+
+    let (sender, receiver) = MidiClass::new(builder, 1, 1, 64).split();
+    inner(sender, receiver)
+}
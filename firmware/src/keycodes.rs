@@ -1,25 +1,50 @@
-//! Extra keycodes for controller
+//! Extra keycodes for controller.
+//! Via custom keycodes are generated from these in order, docs are in `Title (Name)` form.
 #![allow(unused)]
 
 use keycode::Keycode;
 
+/// Gamepad Button 1 (Joystick Btn1)
 pub const JOY_BTN1: Keycode = Keycode(0x7E00);
+/// Gamepad Button 2 (Joystick Btn2)
 pub const JOY_BTN2: Keycode = Keycode(0x7E01);
+/// Gamepad Button 3 (Joystick Btn3)
 pub const JOY_BTN3: Keycode = Keycode(0x7E02);
+/// Gamepad Button 4 (Joystick Btn4)
 pub const JOY_BTN4: Keycode = Keycode(0x7E03);
+/// Gamepad Button 5 (Joystick Btn5)
 pub const JOY_BTN5: Keycode = Keycode(0x7E04);
+/// Gamepad Button 6 (Joystick Btn6)
 pub const JOY_BTN6: Keycode = Keycode(0x7E05);
+/// Gamepad Button 7 (Joystick Btn7)
 pub const JOY_BTN7: Keycode = Keycode(0x7E06);
+/// Gamepad Button 8 (Joystick Btn8)
 pub const JOY_BTN8: Keycode = Keycode(0x7E07);
+/// Gamepad Button 9 (Joystick Btn9)
 pub const JOY_BTN9: Keycode = Keycode(0x7E08);
+/// Gamepad Button 10 (Joystick Btn10)
 pub const JOY_BTN10: Keycode = Keycode(0x7E09);
+/// Gamepad Button 11 (Joystick Btn11)
 pub const JOY_BTN11: Keycode = Keycode(0x7E0A);
+/// Gamepad Button 12 (Joystick Btn12)
 pub const JOY_BTN12: Keycode = Keycode(0x7E0B);
+/// Gamepad Button 13 (Joystick Btn13)
 pub const JOY_BTN13: Keycode = Keycode(0x7E0C);
+/// Gamepad Button 14 (Joystick Btn14)
 pub const JOY_BTN14: Keycode = Keycode(0x7E0D);
+/// Gamepad Button 15 (Joystick Btn15)
 pub const JOY_BTN15: Keycode = Keycode(0x7E0E);
+/// Gamepad Button 16 (Joystick Btn16)
 pub const JOY_BTN16: Keycode = Keycode(0x7E0F);
+/// Gamepad Dpad Up (Dpad Up)
 pub const DPAD_UP: Keycode = Keycode(0x7E10);
+/// Gamepad Dpad Left (Dpad Left)
 pub const DPAD_LEFT: Keycode = Keycode(0x7E11);
+/// Gamepad Dpad Down (Dpad Down)
 pub const DPAD_DOWN: Keycode = Keycode(0x7E12);
+/// Gamepad Dpad Right (Dpad Right)
 pub const DPAD_RIGHT: Keycode = Keycode(0x7E13);
+/// EAC Service Button (EAC Service)
+pub const EAC_SERVICE: Keycode = Keycode(0x7E14);
+/// EAC Test Button (EAC Test)
+pub const EAC_TEST: Keycode = Keycode(0x7E15);
@@ -1,29 +1,29 @@
-use embassy_futures::join::join;
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use embassy_rp::{
-    Peri,
-    gpio::{Level, Output, Pin},
-    peripherals::*,
+    gpio::Level,
+    pwm::{self, PwmOutput},
 };
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Instant, Ticker, Timer};
+use embedded_hal::pwm::SetDutyCycle;
+
+use crate::{
+    log,
+    userdata::{
+        self,
+        led::{LedEffect, LedSettings},
+    },
+};
 
-use embassy_time::{Duration, Ticker};
-
-pub struct LedConfig {
-    /// LED pinout
-    pub pins: LedPinout,
-}
-
-pub struct LedPinout {
-    pub button_1: Peri<'static, PIN_8>,
-    pub button_2: Peri<'static, PIN_9>,
-    pub button_3: Peri<'static, PIN_10>,
-    pub button_4: Peri<'static, PIN_11>,
-
-    pub fx_1: Peri<'static, PIN_12>,
-    pub fx_2: Peri<'static, PIN_13>,
+/// Full period of breathing effect
+const BREATHING_PERIOD_MS: u64 = 4000;
+/// Duration each lamp is lit during diagnostics walk
+const DIAGNOSTICS_WALK_MS: u64 = 300;
 
-    pub start: Peri<'static, PIN_14>,
-}
+/// Lamps of board, built by [`board::led_pinout`](crate::board::led_pinout).
+/// In [`LedState::levels`] order, `None` if the lamp is not on board.
+pub struct LedPinout(pub [Option<PwmOutput<'static>>; 7]);
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct LedState {
@@ -52,6 +52,21 @@ impl Default for LedState {
     }
 }
 
+impl LedState {
+    /// Lamp levels in [`LedPinout`] order: button 1-4, FX 1-2, start
+    pub const fn levels(&self) -> [Level; 7] {
+        [
+            self.button_1,
+            self.button_2,
+            self.button_3,
+            self.button_4,
+            self.fx_1,
+            self.fx_2,
+            self.start,
+        ]
+    }
+}
+
 static LED_STATE: Signal<CriticalSectionRawMutex, LedState> = Signal::new();
 
 #[inline]
@@ -59,38 +74,130 @@ pub fn update(led: LedState) {
     LED_STATE.signal(led);
 }
 
+static DIAGNOSTICS: AtomicBool = AtomicBool::new(false);
+static DIAGNOSTICS_START: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static DIAGNOSTICS_STATE: Signal<CriticalSectionRawMutex, LedState> = Signal::new();
+
+/// Start LED diagnostics.
+/// Each lamp is lit one by one, then lamps follow the raw inputs until diagnostics is stopped.
+pub fn start_diagnostics() {
+    DIAGNOSTICS.store(true, Ordering::Relaxed);
+    DIAGNOSTICS_START.signal(());
+}
+
+/// Stop LED diagnostics and return to normal operation
+pub fn stop_diagnostics() {
+    DIAGNOSTICS.store(false, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn diagnostics_running() -> bool {
+    DIAGNOSTICS.load(Ordering::Relaxed)
+}
+
+/// Update lamps shown during diagnostics
+#[inline]
+pub fn update_diagnostics(led: LedState) {
+    DIAGNOSTICS_STATE.signal(led);
+}
+
 #[embassy_executor::task]
-pub async fn led_task(cfg: LedConfig) {
-    let mut button_1 = led(cfg.pins.button_1);
-    let mut button_2 = led(cfg.pins.button_2);
-    let mut button_3 = led(cfg.pins.button_3);
-    let mut button_4 = led(cfg.pins.button_4);
-    let mut fx_1 = led(cfg.pins.fx_1);
-    let mut fx_2 = led(cfg.pins.fx_2);
-    let mut start = led(cfg.pins.start);
-
-    let mut last_state = LedState::default();
+pub async fn led_task(pins: LedPinout) {
+    let mut lamps = pins.0;
+
+    let mut state = LedState::default();
+    let mut diagnostics_state = LedState::default();
     let mut ticker = Ticker::every(Duration::from_millis(8));
     loop {
         // Limit updates maximum 125Hz
-        let (state, _) = join(LED_STATE.wait(), ticker.next()).await;
-        if state == last_state {
+        ticker.next().await;
+
+        if DIAGNOSTICS_START.try_take().is_some() {
+            log::info!("Running LED diagnostics.");
+            walk_lamps(&mut lamps).await;
+            diagnostics_state = LedState::default();
+            ticker.reset();
+        }
+
+        if let Some(next) = LED_STATE.try_take() {
+            state = next;
+        }
+        if let Some(next) = DIAGNOSTICS_STATE.try_take() {
+            diagnostics_state = next;
+        }
+
+        if diagnostics_running() {
+            // Show inputs in full brightness
+            for (lamp, level) in lamps.iter_mut().zip(diagnostics_state.levels()) {
+                if let Some(lamp) = lamp {
+                    set_lamp(lamp, level, u8::MAX, 0);
+                }
+            }
             continue;
         }
 
-        button_1.set_level(state.button_1);
-        button_2.set_level(state.button_2);
-        button_3.set_level(state.button_3);
-        button_4.set_level(state.button_4);
-        fx_1.set_level(state.fx_1);
-        fx_2.set_level(state.fx_2);
-        start.set_level(state.start);
+        let settings = userdata::get(|data| data.led);
+        let (on, off) = levels(&settings);
+        for (lamp, level) in lamps.iter_mut().zip(state.levels()) {
+            if let Some(lamp) = lamp {
+                set_lamp(lamp, level, on, off);
+            }
+        }
+    }
+}
+
+/// Light each lamp one by one
+async fn walk_lamps(lamps: &mut [Option<PwmOutput<'static>>]) {
+    for lamp in lamps.iter_mut().flatten() {
+        set_lamp(lamp, Level::Low, 0, 0);
+    }
+
+    for lamp in lamps.iter_mut().flatten() {
+        set_lamp(lamp, Level::High, u8::MAX, 0);
+        Timer::after_millis(DIAGNOSTICS_WALK_MS).await;
+        set_lamp(lamp, Level::Low, u8::MAX, 0);
+    }
+}
 
-        last_state = state;
+/// Compute brightness of lit and unlit lamps
+fn levels(settings: &LedSettings) -> (u8, u8) {
+    match settings.effect {
+        LedEffect::Static => (settings.brightness, 0),
+
+        LedEffect::Breathing => {
+            let phase = Instant::now().as_millis() % BREATHING_PERIOD_MS;
+            let half = BREATHING_PERIOD_MS / 2;
+            // Triangle wave from 0 to 255
+            let wave = if phase < half {
+                phase * 255 / half
+            } else {
+                (BREATHING_PERIOD_MS - phase) * 255 / half
+            };
+
+            (
+                settings.brightness,
+                (settings.brightness as u64 * wave / 255) as u8,
+            )
+        }
     }
 }
 
-#[inline(always)]
-fn led<'a>(pin: Peri<'a, impl Pin>) -> Output<'a> {
-    Output::new(pin, Level::Low)
+#[inline]
+fn set_lamp(output: &mut PwmOutput<'_>, level: Level, on: u8, off: u8) {
+    let brightness = match level {
+        Level::High => on,
+        Level::Low => off,
+    } as u16;
+
+    // Apply gamma 2 correction
+    _ = output.set_duty_cycle_fraction(brightness * brightness, 255 * 255);
+}
+
+/// PWM config of a slice. Channels of active low lamps are inverted
+pub fn pwm_config(invert_a: bool, invert_b: bool) -> pwm::Config {
+    let mut config = pwm::Config::default();
+    config.top = u16::MAX;
+    config.invert_a = invert_a;
+    config.invert_b = invert_b;
+    config
 }
@@ -0,0 +1,130 @@
+//! Logging macros writing both defmt frames and plain text lines.
+//! Text lines are only formatted when [`LogFormat::Text`] is selected.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, pipe::Pipe};
+use embassy_time::Instant;
+use embassy_usb::class::cdc_acm::Sender;
+use heapless::String;
+
+use crate::{usb::Driver, userdata::LogFormat};
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {{
+        defmt::debug!($($arg)*);
+        $crate::log::text($crate::log::Level::Debug, format_args!($($arg)*));
+    }};
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {{
+        defmt::info!($($arg)*);
+        $crate::log::text($crate::log::Level::Info, format_args!($($arg)*));
+    }};
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {{
+        defmt::warn!($($arg)*);
+        $crate::log::text($crate::log::Level::Warn, format_args!($($arg)*));
+    }};
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        defmt::error!($($arg)*);
+        $crate::log::text($crate::log::Level::Error, format_args!($($arg)*));
+    }};
+}
+
+// `warn` conflicts with builtin attribute unless renamed
+pub(crate) use {log_debug as debug, log_error as error, log_info as info, log_warn as warn};
+
+#[derive(Clone, Copy)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO ",
+            Level::Warn => "WARN ",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+static TEXT: AtomicBool = AtomicBool::new(false);
+
+/// Buffered text lines waiting for host
+static PIPE: Pipe<CriticalSectionRawMutex, 1024> = Pipe::new();
+
+/// Select log format. Must be called before logger task starts.
+pub fn init(format: LogFormat) {
+    TEXT.store(format == LogFormat::Text, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn text_enabled() -> bool {
+    TEXT.load(Ordering::Relaxed)
+}
+
+/// Write text line. Dropped if buffer is full.
+pub fn text(level: Level, args: fmt::Arguments) {
+    if !text_enabled() {
+        return;
+    }
+
+    let mut line = String::<128>::new();
+    let now = Instant::now().as_micros();
+    _ = write!(
+        line,
+        "[{}.{:06} {}] ",
+        now / 1_000_000,
+        now % 1_000_000,
+        level.as_str()
+    );
+    // Too long lines are truncated
+    _ = line.write_fmt(args);
+
+    critical_section::with(|_| {
+        if PIPE.free_capacity() < line.len() + 2 {
+            return;
+        }
+
+        _ = PIPE.try_write(line.as_bytes());
+        _ = PIPE.try_write(b"\r\n");
+    });
+}
+
+/// Send text lines to CDC ACM port
+pub async fn text_logger(mut sender: Sender<'static, Driver>) {
+    let max_packet_size = sender.max_packet_size() as usize;
+    let mut buf = [0_u8; 64];
+
+    loop {
+        sender.wait_connection().await;
+
+        loop {
+            let len = PIPE.read(&mut buf[..max_packet_size]).await;
+            if sender.write_packet(&buf[..len]).await.is_err() {
+                break;
+            }
+
+            // Terminate transfer ending with full packet
+            if len == max_packet_size && PIPE.is_empty() && sender.write_packet(&[]).await.is_err()
+            {
+                break;
+            }
+        }
+    }
+}
@@ -1,25 +1,32 @@
 #![no_std]
 #![no_main]
 
+mod board;
+mod chip;
+mod console;
+mod crash;
 mod input;
 mod keycodes;
 mod led;
+mod log;
 mod logger;
 mod usb;
 mod userdata;
 mod via;
+mod watchdog;
 
 use crate::{
     input::{
-        config::InputPinout,
+        hotkey,
         reader::{
             button::{self, ButtonInputReader},
             knob::KnobInputReader,
         },
     },
-    led::{LedConfig, LedPinout, led_task},
+    led::led_task,
     usb::init_usb,
     userdata::init_userdata,
+    watchdog::watchdog_task,
 };
 use embassy_executor::{Executor, Spawner};
 use embassy_rp::{
@@ -33,7 +40,7 @@ use embassy_rp::{
 use embassy_time::Timer;
 use static_cell::StaticCell;
 
-use {defmt_embassy_usbserial as _, panic_halt as _};
+use defmt_embassy_usbserial as _;
 
 bind_interrupts!(struct Irqs {
     USBCTRL_IRQ => embassy_rp::usb::InterruptHandler<USB>;
@@ -44,16 +51,16 @@ bind_interrupts!(struct Irqs {
 async fn main(spawner: Spawner) {
     // Boot Phase
     let p = embassy_rp::init(Default::default());
-    defmt::info!("System booted.");
+    log::info!("System booted.");
 
     // System initialization phase
-    defmt::info!("Initializing USB driver...");
+    log::info!("Initializing USB driver...");
     let driver = UsbDriver::new(p.USB, Irqs);
-    defmt::info!("USB driver initialized.");
+    log::info!("USB driver initialized.");
 
-    defmt::info!("Initializing Adc...");
+    log::info!("Initializing Adc...");
     let adc = Adc::new(p.ADC, Irqs, adc::Config::default());
-    defmt::info!("Adc initialized.");
+    log::info!("Adc initialized.");
 
     // add some delay to give an attached debug probe time to parse the
     // defmt RTT header. Reading that header might touch flash memory, which
@@ -61,88 +68,69 @@ async fn main(spawner: Spawner) {
     // https://github.com/knurling-rs/defmt/pull/683
     Timer::after_millis(10).await;
 
-    defmt::info!("Initializing userdata...");
+    log::info!("Initializing userdata...");
     let userdata_task = init_userdata(p.FLASH, p.DMA_CH1).await;
     spawner.must_spawn(userdata_task);
-    defmt::info!("Userdata initialized.");
+    log::init(userdata::get(|data| data.log_format));
+    log::info!("Userdata initialized.");
 
-    defmt::info!("System initialized.");
+    crash::init();
 
-    // Controller initialization phase
-    defmt::info!("Initializing Controller...");
-
-    defmt::info!("Initializing input...");
-    let (buttons, knobs) = InputPinout {
-        button1: p.PIN_0,
-        button2: p.PIN_1,
-        button3: p.PIN_2,
-        button4: p.PIN_3,
-
-        fx1: p.PIN_4,
-        fx2: p.PIN_5,
+    log::info!("System initialized.");
 
-        start: p.PIN_6,
+    // Controller initialization phase
+    log::info!("Initializing Controller...");
 
-        left_knob: p.PIN_26,
-        right_knob: p.PIN_27,
-    }
-    .inputs();
+    log::info!("Initializing input...");
+    let (buttons, knobs) = board::input_pinout!(p).inputs();
 
     read_mode_hotkey(&buttons);
+    read_diagnostics_hotkey(&buttons);
 
     let button_reader = ButtonInputReader::new(buttons);
     let knob_reader = KnobInputReader::new(knobs, adc, p.DMA_CH0);
-    defmt::info!("Input initialized.");
+    log::info!("Input initialized.");
 
-    defmt::info!("Initializing USB...");
+    log::info!("Initializing USB...");
     let usb_task = init_usb(spawner, button_reader, knob_reader, driver);
-    defmt::info!("USB Initialized.");
+    log::info!("USB Initialized.");
 
-    defmt::info!("Initializing Core 1...");
+    log::info!("Initializing Core 1...");
     start_core1(p.CORE1, |spawner| {
-        defmt::info!("Initializing LED...");
-        spawner.must_spawn(led_task(LedConfig {
-            pins: LedPinout {
-                button_1: p.PIN_8,
-                button_2: p.PIN_9,
-                button_3: p.PIN_10,
-                button_4: p.PIN_11,
-                fx_1: p.PIN_12,
-                fx_2: p.PIN_13,
-                start: p.PIN_14,
-            },
-        }));
-        defmt::info!("LED initialized.");
+        log::info!("Initializing LED...");
+        spawner.must_spawn(led_task(board::led_pinout!(p)));
+        log::info!("LED initialized.");
+
+        log::info!("Starting watchdog...");
+        spawner.must_spawn(watchdog_task(p.WATCHDOG));
+        log::info!("Watchdog started.");
     });
-    defmt::info!("Core 1 initialized.");
+    log::info!("Core 1 initialized.");
 
-    defmt::info!("Controller started.");
+    log::info!("Controller started.");
     usb_task.await;
 }
 
 fn read_mode_hotkey(button: &button::Buttons) {
-    if !button.start.input.is_high() {
+    let Some(mode) = hotkey::mode_hotkey(&button.read_raw()) else {
         return;
-    }
-    let prev_eac_mode = userdata::get(|data| data.eac_mode);
-
-    // Start + Button 1: Enable EAC mode
-    if button.button1.input.is_high() && !prev_eac_mode {
-        userdata::update(|data| {
-            data.eac_mode = true;
-        });
-        userdata::save();
+    };
 
-        defmt::info!("EAC mode enabled via hotkey.");
+    if userdata::get(|data| data.mode) == mode {
+        return;
+    }
+    userdata::update(|data| {
+        data.mode = mode;
+    });
+    userdata::save();
 
-    // Start + Button 2: Enable HID mode
-    } else if button.button2.input.is_high() && prev_eac_mode {
-        userdata::update(|data| {
-            data.eac_mode = false;
-        });
-        userdata::save();
+    log::info!("{:?} mode enabled via hotkey.", mode);
+}
 
-        defmt::info!("HID mode enabled via hotkey.");
+fn read_diagnostics_hotkey(button: &button::Buttons) {
+    if button.read_raw().mask() == hotkey::DIAGNOSTICS_HOTKEY {
+        led::start_diagnostics();
+        log::info!("LED diagnostics enabled via hotkey.");
     }
 }
 
@@ -1,6 +1,9 @@
 pub mod config;
 pub mod eac;
 pub mod hid;
+pub mod midi;
+pub mod switch;
+pub mod xinput;
 
 use embassy_executor::Spawner;
 use embassy_rp::{peripherals::USB, usb::Driver as UsbDriver};
@@ -8,13 +11,17 @@ use embassy_usb::{Builder, Handler, types::StringIndex};
 use static_cell::{ConstStaticCell, StaticCell};
 
 use crate::{
+    console::console_task,
     input::{
-        eac_input_task, hid_input_task,
+        eac_input_task, hid_input_task, midi_input_task,
         reader::{button::ButtonInputReader, knob::KnobInputReader},
+        switch_input_task, xinput_input_task,
     },
+    log,
     logger::logger_task,
-    userdata,
+    userdata::{self, UsbMode},
     via::via_task,
+    watchdog,
 };
 
 pub type Driver = UsbDriver<'static, USB>;
@@ -26,19 +33,21 @@ pub fn init_usb(
     driver: Driver,
 ) -> impl Future + 'static {
     // Allocates descriptor and control buffer
-    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 512]> = StaticCell::new();
     static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
     static MSOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
     static CONTROL_BUF: StaticCell<[u8; config::DEVICE.max_packet_size_0 as usize]> =
         StaticCell::new();
 
-    let eac_mode = userdata::get(|data| data.eac_mode);
+    let mode = userdata::get(|data| data.mode);
     let mut builder = embassy_usb::Builder::new(
         driver,
-        if eac_mode {
-            config::EAC_DEVICE
-        } else {
-            config::DEVICE
+        match mode {
+            UsbMode::Hid => config::DEVICE,
+            UsbMode::Eac => config::EAC_DEVICE,
+            UsbMode::XInput => config::XINPUT_DEVICE,
+            UsbMode::Switch => config::SWITCH_DEVICE,
+            UsbMode::Midi => config::MIDI_DEVICE,
         },
         CONFIG_DESCRIPTOR.init([0; _]),
         BOS_DESCRIPTOR.init([0; _]),
@@ -46,45 +55,104 @@ pub fn init_usb(
         CONTROL_BUF.init([0; _]),
     );
 
-    // Setup logger task
-    spawner.must_spawn(logger_task(&mut builder));
-
-    if eac_mode {
-        // Setup EAC input task
-        spawner.must_spawn(eac_input_task(
-            spawner,
-            button_reader,
-            knob_reader,
-            &mut builder,
-        ));
-    } else {
-        // Setup HID input task
-        spawner.must_spawn(hid_input_task(
-            spawner,
-            button_reader,
-            knob_reader,
-            &mut builder,
-        ));
-
-        // Setup via task
-        spawner.must_spawn(via_task(&mut builder));
+    match mode {
+        UsbMode::Hid => {
+            // Setup logger task
+            spawner.must_spawn(logger_task(&mut builder));
+
+            // Setup console task
+            spawner.must_spawn(console_task(&mut builder));
+
+            // Setup HID input task
+            spawner.must_spawn(hid_input_task(
+                spawner,
+                button_reader,
+                knob_reader,
+                &mut builder,
+            ));
+
+            // Setup via task
+            spawner.must_spawn(via_task(&mut builder));
+        }
+
+        UsbMode::Eac => {
+            // Setup logger task
+            spawner.must_spawn(logger_task(&mut builder));
+
+            // Setup console task
+            spawner.must_spawn(console_task(&mut builder));
+
+            // Setup EAC input task
+            spawner.must_spawn(eac_input_task(
+                spawner,
+                button_reader,
+                knob_reader,
+                &mut builder,
+            ));
+        }
+
+        // XInput device is not a composite device, so logger interface is not available.
+        UsbMode::XInput => {
+            // Setup XInput input task
+            spawner.must_spawn(xinput_input_task(
+                spawner,
+                button_reader,
+                knob_reader,
+                &mut builder,
+            ));
+        }
+
+        // Switch only accepts single HID interface device.
+        UsbMode::Switch => {
+            // Setup Switch input task
+            spawner.must_spawn(switch_input_task(
+                spawner,
+                button_reader,
+                knob_reader,
+                &mut builder,
+            ));
+        }
+
+        UsbMode::Midi => {
+            // Setup logger task
+            spawner.must_spawn(logger_task(&mut builder));
+
+            // Setup console task
+            spawner.must_spawn(console_task(&mut builder));
+
+            // Setup MIDI input task
+            spawner.must_spawn(midi_input_task(
+                spawner,
+                button_reader,
+                knob_reader,
+                &mut builder,
+            ));
+        }
     }
 
     let handler = setup_handler(&mut builder);
     builder.handler(handler);
 
     let mut device = builder.build();
-    async move { device.run().await }
+    async move {
+        device.run().await;
+    }
 }
 
 struct UsbHandler;
 impl Handler for UsbHandler {
     fn addressed(&mut self, addr: u8) {
-        defmt::info!("USB Addressed: {}", addr);
+        log::info!("USB Addressed: {}", addr);
     }
 
     fn configured(&mut self, configured: bool) {
-        defmt::info!("USB Configured: {}", configured);
+        log::info!("USB Configured: {}", configured);
+        watchdog::set_usb_configured(configured);
+    }
+
+    fn suspended(&mut self, suspended: bool) {
+        log::info!("USB Suspended: {}", suspended);
+        watchdog::set_usb_suspended(suspended);
     }
 
     fn get_string(&mut self, index: StringIndex, _lang_id: u16) -> Option<&str> {
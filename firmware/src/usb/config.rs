@@ -1,18 +1,28 @@
+use embassy_usb::UsbVersion;
 use static_cell::ConstStaticCell;
 use usbd_hid::descriptor::{KeyboardReport, MouseReport, SerializedDescriptor};
 
-use crate::usb::{
-    eac::{self, EacHidHandler},
-    hid::{GamepadInputReport, QmkRawHidReport},
+use crate::{
+    usb::{
+        eac::{self, EacHidHandler},
+        hid::{GamepadInputReport, QmkRawHidReport},
+        switch::SwitchInputReport,
+    },
+    userdata::{self, poll::PollIntervals},
 };
 
 pub const DEVICE: embassy_usb::Config = hid_device_config();
 pub const EAC_DEVICE: embassy_usb::Config = eac_device_config();
+pub const XINPUT_DEVICE: embassy_usb::Config = xinput_device_config();
+pub const SWITCH_DEVICE: embassy_usb::Config = switch_device_config();
+pub const MIDI_DEVICE: embassy_usb::Config = midi_device_config();
 
 const fn hid_device_config() -> embassy_usb::Config<'static> {
     let mut config = embassy_usb::Config::new(0x3d5a, 0xcafe);
     config.manufacturer = Some("SDVX-Con");
     config.product = Some("SDVX Controller");
+    // Vial GUI detects keyboards by serial number prefix
+    config.serial_number = Some("vial:f64c2b3c");
 
     device_config(config)
 }
@@ -26,6 +36,54 @@ const fn eac_device_config() -> embassy_usb::Config<'static> {
     device_config(config)
 }
 
+const fn xinput_device_config() -> embassy_usb::Config<'static> {
+    let mut config = embassy_usb::Config::new(0x045e, 0x028e);
+    config.manufacturer = Some("©Microsoft Corporation");
+    config.product = Some("Controller");
+    config.serial_number = Some("SDVX");
+    config.device_release = 0x0114;
+    config.bcd_usb = UsbVersion::Two;
+
+    // XInput device is vendor specific, not a composite device
+    config.device_class = 0xff;
+    config.device_sub_class = 0xff;
+    config.device_protocol = 0xff;
+    config.composite_with_iads = false;
+
+    device_config(config)
+}
+
+const fn switch_device_config() -> embassy_usb::Config<'static> {
+    let mut config = embassy_usb::Config::new(0x0f0d, 0x00c1);
+    config.manufacturer = Some("HORI CO.,LTD.");
+    config.product = Some("HORIPAD S");
+    config.device_release = 0x0572;
+    config.bcd_usb = UsbVersion::Two;
+
+    // Single HID interface device
+    config.device_class = 0x00;
+    config.device_sub_class = 0x00;
+    config.device_protocol = 0x00;
+    config.composite_with_iads = false;
+
+    device_config(config)
+}
+
+const fn midi_device_config() -> embassy_usb::Config<'static> {
+    // Separate product id, so hosts don't reuse cached HID mode descriptors
+    let mut config = embassy_usb::Config::new(0x3d5a, 0xcaff);
+    config.manufacturer = Some("SDVX-Con");
+    config.product = Some("SDVX Controller MIDI");
+
+    device_config(config)
+}
+
+/// Polling interval of an interface from userdata
+pub fn poll_ms(f: impl FnOnce(&PollIntervals) -> u8) -> u8 {
+    // Interrupt endpoints require at least 1ms
+    userdata::get(|data| f(&data.poll)).max(1)
+}
+
 /// Common USB device configuration
 const fn device_config(mut config: embassy_usb::Config<'static>) -> embassy_usb::Config<'static> {
     config.max_power = 100;
@@ -41,7 +99,7 @@ pub fn eac<'a>() -> embassy_usb::class::hid::Config<'a> {
     embassy_usb::class::hid::Config {
         report_descriptor: eac::EAC_HID_DESC,
         request_handler: Some(HANDLER.take()),
-        poll_ms: 1,
+        poll_ms: poll_ms(|poll| poll.eac),
         max_packet_size: 8,
     }
 }
@@ -50,16 +108,25 @@ pub fn gamepad<'a>() -> embassy_usb::class::hid::Config<'a> {
     embassy_usb::class::hid::Config {
         report_descriptor: GamepadInputReport::desc(),
         request_handler: None,
-        poll_ms: 1,
+        poll_ms: poll_ms(|poll| poll.gamepad),
         max_packet_size: const { size_of::<GamepadInputReport>() as u16 },
     }
 }
 
+pub fn switch<'a>() -> embassy_usb::class::hid::Config<'a> {
+    embassy_usb::class::hid::Config {
+        report_descriptor: SwitchInputReport::desc(),
+        request_handler: None,
+        poll_ms: poll_ms(|poll| poll.switch),
+        max_packet_size: 64,
+    }
+}
+
 pub fn keyboard<'a>() -> embassy_usb::class::hid::Config<'a> {
     embassy_usb::class::hid::Config {
         report_descriptor: KeyboardReport::desc(),
         request_handler: None,
-        poll_ms: 1,
+        poll_ms: poll_ms(|poll| poll.keyboard),
         max_packet_size: const { size_of::<KeyboardReport>() as u16 },
     }
 }
@@ -68,7 +135,7 @@ pub fn mouse<'a>() -> embassy_usb::class::hid::Config<'a> {
     embassy_usb::class::hid::Config {
         report_descriptor: MouseReport::desc(),
         request_handler: None,
-        poll_ms: 1,
+        poll_ms: poll_ms(|poll| poll.mouse),
         max_packet_size: const { size_of::<MouseReport>() as u16 },
     }
 }
@@ -77,7 +144,7 @@ pub fn via<'a>() -> embassy_usb::class::hid::Config<'a> {
     embassy_usb::class::hid::Config {
         report_descriptor: QmkRawHidReport::desc(),
         request_handler: None,
-        poll_ms: 8,
+        poll_ms: poll_ms(|poll| poll.via),
         max_packet_size: const { size_of::<QmkRawHidReport>() as u16 },
     }
 }
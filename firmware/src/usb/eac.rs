@@ -6,7 +6,10 @@ use embassy_usb::{
 use usbd_hid::descriptor::generator_prelude::*;
 use zerocopy::{FromBytes, Immutable, KnownLayout};
 
-use crate::led::{self, LedState};
+use crate::{
+    led::{self, LedState},
+    log,
+};
 
 #[rustfmt::skip]
 pub const EAC_HID_DESC: &[u8] = &[
@@ -180,7 +183,7 @@ pub const EAC_HID_DESC: &[u8] = &[
 ];
 
 /// HID Input report for EAC mode
-#[derive(Default, PartialEq, Eq)]
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct EacInputReport {
     /// Report ID (4)
     pub report_id: u8,
@@ -272,7 +275,7 @@ impl RequestHandler for EacHidHandler {
                     return OutResponse::Rejected;
                 };
                 self.led_mode = report.mode;
-                defmt::info!("EAC LED mode set to {}", report.mode);
+                log::info!("EAC LED mode set to {}", report.mode);
 
                 OutResponse::Accepted
             }
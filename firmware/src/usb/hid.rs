@@ -2,7 +2,7 @@ use usbd_hid::descriptor::{SerializedDescriptor, generator_prelude::*};
 use zerocopy::{FromBytes, Immutable, IntoBytes};
 
 /// HID report and descriptor for a gamepad with buttons and D-pad.
-#[derive(Default, PartialEq, Eq)]
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct GamepadInputReport {
     /// Button states from button 1 to button 16
     pub buttons: u16,
@@ -0,0 +1,33 @@
+/// USB MIDI event packet, see USB MIDI 1.0 specification section 4
+pub type MidiPacket = [u8; 4];
+
+/// Code index numbers
+const CIN_NOTE_OFF: u8 = 0x08;
+const CIN_NOTE_ON: u8 = 0x09;
+const CIN_CONTROL_CHANGE: u8 = 0x0B;
+
+/// Velocity of note on messages
+const VELOCITY: u8 = 0x7F;
+
+pub const fn note_on(channel: u8, note: u8) -> MidiPacket {
+    packet(CIN_NOTE_ON, channel, note, VELOCITY)
+}
+
+pub const fn note_off(channel: u8, note: u8) -> MidiPacket {
+    packet(CIN_NOTE_OFF, channel, note, 0)
+}
+
+pub const fn control_change(channel: u8, cc: u8, value: u8) -> MidiPacket {
+    packet(CIN_CONTROL_CHANGE, channel, cc, value)
+}
+
+/// Build packet on virtual cable 0
+#[inline]
+const fn packet(cin: u8, channel: u8, data1: u8, data2: u8) -> MidiPacket {
+    [
+        cin,
+        (cin << 4) | (channel & 0x0F),
+        data1 & 0x7F,
+        data2 & 0x7F,
+    ]
+}
@@ -0,0 +1,111 @@
+use usbd_hid::descriptor::{SerializedDescriptor, generator_prelude::*};
+
+/// HID report and descriptor compatible with HORIPAD for Nintendo Switch.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SwitchInputReport {
+    /// Button states from button 1 to button 14.
+    /// Y, B, A, X, L, R, ZL, ZR, Minus, Plus, Left stick, Right stick, Home, Capture
+    pub buttons: u16,
+
+    /// Hat switch state (0-8)
+    /// 0: up, 1: up-right, 2: right, 3: down-right, 4: down, 5: down-left, 6: left, 7: up-left, 8: centered
+    pub hat: u8,
+
+    /// Stick axes (0-255). 128 is center.
+    pub left_x: u8,
+    pub left_y: u8,
+    pub right_x: u8,
+    pub right_y: u8,
+
+    /// Vendor specific
+    pub vendor: u8,
+}
+
+impl SwitchInputReport {
+    pub const HAT_CENTER: u8 = 0x08;
+    pub const STICK_CENTER: u8 = 0x80;
+
+    pub const DEFAULT: Self = Self {
+        buttons: 0,
+        hat: Self::HAT_CENTER,
+        left_x: Self::STICK_CENTER,
+        left_y: Self::STICK_CENTER,
+        right_x: Self::STICK_CENTER,
+        right_y: Self::STICK_CENTER,
+        vendor: 0,
+    };
+}
+
+impl Default for SwitchInputReport {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl SerializedDescriptor for SwitchInputReport {
+    #[rustfmt::skip]
+    fn desc() -> &'static [u8] {
+        &[
+            0x05, 0x01, //      Usage Page (Generic Desktop Ctrls)
+            0x09, 0x05, //      Usage (Game Pad)
+            0xA1, 0x01, //      Collection (Application)
+            0x15, 0x00, //          Logical Minimum (0)
+            0x25, 0x01, //          Logical Maximum (1)
+            0x35, 0x00, //          Physical Minimum (0)
+            0x45, 0x01, //          Physical Maximum (1)
+            0x75, 0x01, //          Report Size (1)
+            0x95, 0x10, //          Report Count (16)
+            0x05, 0x09, //          Usage Page (Button)
+            0x19, 0x01, //          Usage Minimum (0x01)
+            0x29, 0x10, //          Usage Maximum (0x10)
+            0x81, 0x02, //          Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+            0x05, 0x01, //          Usage Page (Generic Desktop Ctrls)
+            0x25, 0x07, //          Logical Maximum (7)
+            0x46, 0x3B, 0x01, //    Physical Maximum (315)
+            0x75, 0x04, //          Report Size (4)
+            0x95, 0x01, //          Report Count (1)
+            0x65, 0x14, //          Unit (System: English Rotation, Length: Centimeter)
+            0x09, 0x39, //          Usage (Hat switch)
+            0x81, 0x42, //          Input (Data,Var,Abs,No Wrap,Linear,Preferred State,Null State)
+            0x65, 0x00, //          Unit (None)
+            0x95, 0x01, //          Report Count (1)
+            0x81, 0x01, //          Input (Const,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
+            0x26, 0xFF, 0x00, //    Logical Maximum (255)
+            0x46, 0xFF, 0x00, //    Physical Maximum (255)
+            0x09, 0x30, //          Usage (X)
+            0x09, 0x31, //          Usage (Y)
+            0x09, 0x32, //          Usage (Z)
+            0x09, 0x35, //          Usage (Rz)
+            0x75, 0x08, //          Report Size (8)
+            0x95, 0x04, //          Report Count (4)
+            0x81, 0x02, //          Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+            0x06, 0x00, 0xFF, //    Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x20, //          Usage (0x20)
+            0x95, 0x01, //          Report Count (1)
+            0x81, 0x02, //          Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+            0x0A, 0x21, 0x26, //    Usage (0x2621)
+            0x95, 0x08, //          Report Count (8)
+            0x91, 0x02, //          Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+            0xC0, //          End Collection
+        ]
+    }
+}
+
+impl Serialize for SwitchInputReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_tuple(7)?;
+        s.serialize_element(&self.buttons)?;
+        s.serialize_element(&self.hat)?;
+        s.serialize_element(&self.left_x)?;
+        s.serialize_element(&self.left_y)?;
+        s.serialize_element(&self.right_x)?;
+        s.serialize_element(&self.right_y)?;
+        s.serialize_element(&self.vendor)?;
+        s.end()
+    }
+}
+
+impl AsInputReport for SwitchInputReport {}
@@ -0,0 +1,119 @@
+use embassy_usb::driver::{Direction, EndpointAddress};
+use zerocopy::{Immutable, IntoBytes, little_endian};
+
+use crate::usb::Driver;
+
+/// XInput vendor interface class
+const CLASS_VENDOR: u8 = 0xFF;
+const SUBCLASS_XINPUT: u8 = 0x5D;
+const PROTOCOL_XINPUT: u8 = 0x01;
+
+const ENDPOINT_IN: u8 = 1;
+const ENDPOINT_OUT: u8 = 1;
+
+/// Undocumented XInput class descriptor type
+const XINPUT_DESC_TYPE: u8 = 0x21;
+
+/// Undocumented XInput class descriptor from Xbox 360 wired controller.
+/// Describes report endpoints and its sizes.
+#[rustfmt::skip]
+const XINPUT_DESC: &[u8] = &[
+    0x00, 0x01, 0x01, 0x25,
+    0x80 | ENDPOINT_IN, //  Input endpoint
+    0x14, //                Input report size
+    0x00, 0x00, 0x00, 0x00, 0x13,
+    ENDPOINT_OUT, //        Output endpoint
+    0x08, //                Output report size
+    0x00, 0x00,
+];
+
+/// XInput button bits
+pub struct XInputButton;
+impl XInputButton {
+    pub const DPAD_UP: u16 = 0x0001;
+    pub const DPAD_DOWN: u16 = 0x0002;
+    pub const DPAD_LEFT: u16 = 0x0004;
+    pub const DPAD_RIGHT: u16 = 0x0008;
+    pub const START: u16 = 0x0010;
+    pub const BACK: u16 = 0x0020;
+    pub const LEFT_THUMB: u16 = 0x0040;
+    pub const RIGHT_THUMB: u16 = 0x0080;
+    pub const LEFT_SHOULDER: u16 = 0x0100;
+    pub const RIGHT_SHOULDER: u16 = 0x0200;
+    pub const GUIDE: u16 = 0x0400;
+    pub const A: u16 = 0x1000;
+    pub const B: u16 = 0x2000;
+    pub const X: u16 = 0x4000;
+    pub const Y: u16 = 0x8000;
+}
+
+/// XInput input report
+#[derive(Clone, PartialEq, Eq, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct XInputReport {
+    /// Message type (0x00)
+    pub msg_type: u8,
+    /// Report size (20)
+    pub size: u8,
+    /// Button states. See [`XInputButton`]
+    pub buttons: little_endian::U16,
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    pub left_x: little_endian::I16,
+    pub left_y: little_endian::I16,
+    pub right_x: little_endian::I16,
+    pub right_y: little_endian::I16,
+    pub _reserved: [u8; 6],
+}
+
+impl XInputReport {
+    pub const DEFAULT: Self = Self {
+        msg_type: 0x00,
+        size: size_of::<Self>() as u8,
+        buttons: little_endian::U16::ZERO,
+        left_trigger: 0,
+        right_trigger: 0,
+        left_x: little_endian::I16::ZERO,
+        left_y: little_endian::I16::ZERO,
+        right_x: little_endian::I16::ZERO,
+        right_y: little_endian::I16::ZERO,
+        _reserved: [0; 6],
+    };
+}
+
+impl Default for XInputReport {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+pub type XInputEndpointIn = <Driver as embassy_usb::driver::Driver<'static>>::EndpointIn;
+pub type XInputEndpointOut = <Driver as embassy_usb::driver::Driver<'static>>::EndpointOut;
+
+/// Add XInput interface.
+/// Endpoint addresses are fixed since they are described in the class descriptor.
+pub fn xinput_interface(
+    builder: &mut embassy_usb::Builder<'static, Driver>,
+    poll_ms: u8,
+) -> (XInputEndpointIn, XInputEndpointOut) {
+    let mut func = builder.function(CLASS_VENDOR, SUBCLASS_XINPUT, PROTOCOL_XINPUT);
+    let mut iface = func.interface();
+    let mut alt = iface.alt_setting(CLASS_VENDOR, SUBCLASS_XINPUT, PROTOCOL_XINPUT, None);
+    alt.descriptor(XINPUT_DESC_TYPE, XINPUT_DESC);
+
+    let ep_in = alt.endpoint_interrupt_in(
+        Some(EndpointAddress::from_parts(ENDPOINT_IN as _, Direction::In)),
+        32,
+        poll_ms,
+    );
+    let ep_out = alt.endpoint_interrupt_out(
+        Some(EndpointAddress::from_parts(
+            ENDPOINT_OUT as _,
+            Direction::Out,
+        )),
+        32,
+        8,
+    );
+
+    (ep_in, ep_out)
+}
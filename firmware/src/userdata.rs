@@ -1,8 +1,18 @@
+pub mod dynamic;
 mod io;
 pub mod keymap;
+pub mod led;
+pub mod midi;
+pub mod poll;
+pub mod stick;
+pub mod usage;
 
-use core::cell::RefCell;
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
 use embassy_executor::SpawnToken;
+use embassy_futures::select::{Either3, select3};
 use embassy_rp::{
     Peri,
     peripherals::{DMA_CH1, FLASH},
@@ -12,11 +22,32 @@ use embassy_sync::{
     signal::Signal,
     watch::{Receiver, Watch},
 };
-use embassy_time::{Duration, Ticker};
+use embassy_time::{Duration, Instant, Ticker, Timer};
 use scopeguard::defer;
 use zerocopy::{Immutable, IntoBytes, TryFromBytes};
 
-use crate::userdata::{io::UserdataIo, keymap::Keymap};
+use crate::{
+    chip,
+    input::{CURRENT_INPUT, hotkey},
+    log,
+    userdata::{
+        dynamic::DynamicEntries,
+        io::UserdataIo,
+        keymap::{Keymap, LAYER_COUNT},
+        led::LedSettings,
+        midi::MidiSettings,
+        poll::PollIntervals,
+        stick::KnobAxes,
+    },
+};
+
+/// Interval of saving changed usage statistics.
+/// Usage sector is erased at most once per 85 saves.
+const USAGE_SAVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How long inputs must stay idle before usage statistics are written.
+/// Flash operations stall core 0, which also runs the input loop.
+const USAGE_SAVE_IDLE: Duration = Duration::from_secs(1);
 
 /// Magic number for identifying if [`UserData`] in flash is valid or not.
 #[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
@@ -24,24 +55,81 @@ use crate::userdata::{io::UserdataIo, keymap::Keymap};
 pub enum Signature {
     /// Current signature.
     /// Change on every [`UserData`] changes.
-    Current = 0xcaf2deac,
+    Current = 0xcaf2deb9,
+}
+
+/// Number of [`UsbMode`] variants
+pub const USB_MODE_COUNT: usize = 5;
+
+/// USB device mode
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable, Debug, defmt::Format)]
+#[repr(u8)]
+pub enum UsbMode {
+    /// HID composite device with Via support
+    Hid = 0,
+    /// Official SDVX controller compatible device
+    Eac = 1,
+    /// Xbox 360 controller compatible device
+    XInput = 2,
+    /// HORIPAD for Nintendo Switch compatible device
+    Switch = 3,
+    /// USB MIDI device
+    Midi = 4,
+}
+
+impl UsbMode {
+    /// All modes in discriminant order
+    pub const ALL: [Self; USB_MODE_COUNT] =
+        [Self::Hid, Self::Eac, Self::XInput, Self::Switch, Self::Midi];
+}
+
+/// Format of logs sent to the logger serial port
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(u8)]
+pub enum LogFormat {
+    /// Binary defmt frames. Requires firmware ELF to decode.
+    Defmt = 0,
+    /// Plain text lines
+    Text = 1,
 }
 
 #[derive(Clone, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
 #[repr(C)]
 pub struct Userdata {
     pub signature: Signature,
-    pub keymap: Keymap,
-    pub eac_mode: bool,
-    pub _reserved: [u8; 3],
+    /// Keymap of each layer
+    pub keymaps: [Keymap; LAYER_COUNT],
+    pub mode: UsbMode,
+    pub led: LedSettings,
+    pub knob_axes: KnobAxes,
+    pub midi: MidiSettings,
+    pub log_format: LogFormat,
+    pub poll: PollIntervals,
+    pub dynamic: DynamicEntries,
+    /// Time a dual-role key must be held to act as hold
+    pub tapping_term_ms: u16,
+    /// Time all buttons of a combo must be pressed within
+    pub combo_term_ms: u16,
+    /// Boot hotkey of each [`UsbMode`] as button mask, 0 if none
+    pub mode_hotkeys: [u16; USB_MODE_COUNT],
+    pub _reserved: u16,
 }
 
 impl Userdata {
     pub const DEFAULT: Self = Self {
         signature: Signature::Current,
-        keymap: Keymap::DEFAULT,
-        eac_mode: false,
-        _reserved: [0; 3],
+        keymaps: Keymap::DEFAULT_LAYERS,
+        mode: UsbMode::Hid,
+        led: LedSettings::DEFAULT,
+        knob_axes: KnobAxes::DEFAULT,
+        midi: MidiSettings::DEFAULT,
+        log_format: LogFormat::Defmt,
+        poll: PollIntervals::DEFAULT,
+        dynamic: DynamicEntries::DEFAULT,
+        tapping_term_ms: 200,
+        combo_term_ms: 50,
+        mode_hotkeys: hotkey::DEFAULT_MODE_HOTKEYS,
+        _reserved: 0,
     };
 }
 
@@ -80,6 +168,21 @@ pub fn save() {
     SAVE_SIGNAL.signal(());
 }
 
+static USAGE_SAVE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Request to save only usage statistics to flash, once inputs are idle.
+pub fn save_usage() {
+    USAGE_SAVE_SIGNAL.signal(());
+}
+
+static REBOOT: AtomicBool = AtomicBool::new(false);
+
+/// Request to save current [`Userdata`] to flash and reboot after saved.
+pub fn save_and_reboot() {
+    REBOOT.store(true, Ordering::Relaxed);
+    save();
+}
+
 pub async fn init_userdata(
     flash: Peri<'static, FLASH>,
     dma: Peri<'static, DMA_CH1>,
@@ -89,7 +192,7 @@ pub async fn init_userdata(
     let userdata = match io.init().await {
         Ok(data) => data,
         Err(e) => {
-            defmt::error!(
+            log::error!(
                 "Userdata initialization failed error: {:?}. Fallback to default.",
                 e
             );
@@ -100,26 +203,81 @@ pub async fn init_userdata(
         *cell.borrow_mut() = userdata;
     });
 
+    if let Some(stats) = io.init_usage().await {
+        usage::load(stats);
+    }
+
     userdata_task(io)
 }
 
 #[embassy_executor::task]
 async fn userdata_task(mut io: UserdataIo<'static>) {
     let mut ticker = Ticker::every(Duration::from_secs(5));
+    let mut usage_ticker = Ticker::every(USAGE_SAVE_INTERVAL);
     loop {
-        SAVE_SIGNAL.wait().await;
+        match select3(
+            SAVE_SIGNAL.wait(),
+            USAGE_SAVE_SIGNAL.wait(),
+            usage_ticker.next(),
+        )
+        .await
+        {
+            Either3::First(()) => {}
+            Either3::Second(()) | Either3::Third(()) => {
+                wait_input_idle().await;
+                write_usage(&mut io).await;
+                continue;
+            }
+        }
 
         match io.save(&get(|userdata| userdata.clone())).await {
             Ok(_) => {
-                defmt::info!("Userdata saved.");
+                log::info!("Userdata saved.");
             }
 
             Err(e) => {
-                defmt::error!("Failed to save userdata. error: {:?}", e);
+                log::error!("Failed to save userdata. error: {:?}", e);
             }
         }
 
+        write_usage(&mut io).await;
+
+        if REBOOT.load(Ordering::Relaxed) {
+            log::info!("Rebooting...");
+            chip::reboot();
+        }
+
         // Debouncing timer
         ticker.next().await;
     }
 }
+
+/// Wait until no input has been active for [`USAGE_SAVE_IDLE`]
+async fn wait_input_idle() {
+    let mut idle_since = Instant::now();
+    loop {
+        if CURRENT_INPUT.borrow().get().active() {
+            idle_since = Instant::now();
+        } else if idle_since.elapsed() >= USAGE_SAVE_IDLE {
+            return;
+        }
+        Timer::after_millis(50).await;
+    }
+}
+
+/// Save usage statistics if changed
+async fn write_usage(io: &mut UserdataIo<'static>) {
+    let Some(stats) = usage::take_changed() else {
+        return;
+    };
+
+    match io.save_usage(&stats).await {
+        Ok(_) => {
+            log::debug!("Usage statistics saved.");
+        }
+
+        Err(e) => {
+            log::error!("Failed to save usage statistics. error: {:?}", e);
+        }
+    }
+}
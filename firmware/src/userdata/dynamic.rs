@@ -0,0 +1,121 @@
+//! Vial dynamic entries.
+//! Layouts match vial-qmk, so entries are exchanged with Vial as is.
+
+use keycode::Keycode;
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+#[derive(Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct TapDanceEntry {
+    pub on_tap: Keycode,
+    pub on_hold: Keycode,
+    pub on_double_tap: Keycode,
+    pub on_tap_hold: Keycode,
+    pub tapping_term: u16,
+}
+
+impl TapDanceEntry {
+    pub const DEFAULT: Self = Self {
+        on_tap: Keycode::KC_NO,
+        on_hold: Keycode::KC_NO,
+        on_double_tap: Keycode::KC_NO,
+        on_tap_hold: Keycode::KC_NO,
+        tapping_term: 200,
+    };
+
+    pub fn keycodes(&self) -> [Keycode; 4] {
+        [
+            self.on_tap,
+            self.on_hold,
+            self.on_double_tap,
+            self.on_tap_hold,
+        ]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct ComboEntry {
+    /// Keys to press together. Unused slots are [`Keycode::KC_NO`]
+    pub input: [Keycode; 4],
+    pub output: Keycode,
+}
+
+impl ComboEntry {
+    pub const DEFAULT: Self = Self {
+        input: [Keycode::KC_NO; 4],
+        output: Keycode::KC_NO,
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct KeyOverrideEntry {
+    pub trigger: Keycode,
+    pub replacement: Keycode,
+    /// Layer mask the override is active on
+    pub layers: u16,
+    pub trigger_mods: u8,
+    pub negative_mod_mask: u8,
+    pub suppressed_mods: u8,
+    /// Option flags. Bit 7 enables the override
+    pub options: u8,
+}
+
+impl KeyOverrideEntry {
+    /// Any one of trigger mods activates the override, instead of all
+    const OPTION_ONE_MOD: u8 = 1 << 3;
+    const OPTION_ENABLED: u8 = 1 << 7;
+
+    pub const DEFAULT: Self = Self {
+        trigger: Keycode::KC_NO,
+        replacement: Keycode::KC_NO,
+        layers: 0,
+        trigger_mods: 0,
+        negative_mod_mask: 0,
+        suppressed_mods: 0,
+        options: 0,
+    };
+
+    pub fn enabled(&self) -> bool {
+        self.options & Self::OPTION_ENABLED != 0
+    }
+
+    /// Whether held HID modifier bits activate the override.
+    /// As in QMK, a modifier with both sides in trigger mods is matched by either side.
+    pub fn mods_match(&self, mods: u8) -> bool {
+        if mods & self.negative_mod_mask != 0 {
+            return false;
+        }
+
+        if self.options & Self::OPTION_ONE_MOD != 0 {
+            return self.trigger_mods == 0 || mods & self.trigger_mods != 0;
+        }
+
+        let either_side = self.trigger_mods & (self.trigger_mods >> 4) & 0x0F;
+        let one_side = self.trigger_mods & !(either_side | either_side << 4);
+        mods & one_side == one_side && (mods | mods >> 4) & either_side == either_side
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct DynamicEntries {
+    pub tap_dance: [TapDanceEntry; 4],
+    pub combo: [ComboEntry; 8],
+    pub key_override: [KeyOverrideEntry; 4],
+}
+
+impl DynamicEntries {
+    pub const DEFAULT: Self = Self {
+        tap_dance: [TapDanceEntry::DEFAULT; 4],
+        combo: [ComboEntry::DEFAULT; 8],
+        key_override: [KeyOverrideEntry::DEFAULT; 4],
+    };
+}
+
+impl Default for DynamicEntries {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -4,9 +4,55 @@ use embassy_rp::{
     flash::{self, Async, ERASE_SIZE, FLASH_BASE, Flash},
     peripherals::FLASH,
 };
-use zerocopy::{IntoBytes, TryFromBytes};
+use zerocopy::{FromBytes, Immutable, IntoBytes, TryFromBytes};
 
-use crate::userdata::Userdata;
+use crate::{
+    chip, log,
+    userdata::{Userdata, usage::UsageStats},
+};
+
+/// Magic number for identifying written [`UsageRecord`]
+const USAGE_MAGIC: u32 = 0x05a6_e5c0;
+/// Number of records fitting in usage sector
+const USAGE_SLOTS: usize = ERASE_SIZE / size_of::<UsageRecord>();
+
+/// Usage sector entry.
+/// Records are appended to erased slots, and the sector is erased only when full.
+#[derive(FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+struct UsageRecord {
+    magic: u32,
+    stats: UsageStats,
+    checksum: u32,
+    _reserved: u32,
+}
+
+impl UsageRecord {
+    fn new(stats: UsageStats) -> Self {
+        Self {
+            magic: USAGE_MAGIC,
+            stats,
+            checksum: usage_checksum(&stats),
+            _reserved: 0,
+        }
+    }
+
+    /// Slot is not written since last erase
+    fn erased(&self) -> bool {
+        self.as_bytes().iter().all(|&b| b == 0xFF)
+    }
+
+    fn valid(&self) -> bool {
+        self.magic == USAGE_MAGIC && self.checksum == usage_checksum(&self.stats)
+    }
+}
+
+fn usage_checksum(stats: &UsageStats) -> u32 {
+    stats
+        .as_bytes()
+        .iter()
+        .fold(USAGE_MAGIC, |sum, &b| sum.rotate_left(5) ^ b as u32)
+}
 
 #[inline(always)]
 /// Start address of USERDATA memory
@@ -25,14 +71,28 @@ fn userdata_start_offset() -> usize {
     userdata_start() - FLASH_BASE as usize
 }
 
+#[inline]
+/// Offset to start of USAGE memory relative to FLASH memory
+fn usage_start_offset() -> usize {
+    unsafe extern "C" {
+        // Linker defined symbol
+        static __usage_start: u8;
+    }
+
+    &raw const __usage_start as usize - FLASH_BASE as usize
+}
+
 pub struct UserdataIo<'a> {
-    flash: Flash<'a, FLASH, Async, { 2 * 1024 * 1024 }>,
+    flash: Flash<'a, FLASH, Async, { chip::FLASH_SIZE }>,
+    /// Next usage slot to write
+    usage_slot: usize,
 }
 
 impl<'a> UserdataIo<'a> {
     pub fn new(flash: Peri<'a, FLASH>, dma: Peri<'a, impl Channel>) -> Self {
         Self {
             flash: Flash::new(flash, dma),
+            usage_slot: USAGE_SLOTS,
         }
     }
 
@@ -42,7 +102,7 @@ impl<'a> UserdataIo<'a> {
             Some(data) => Ok(data),
             // Saved data is invalid or failed to read
             None => {
-                defmt::info!("Userdata is invalid. Performing initialization.");
+                log::info!("Userdata is invalid. Performing initialization.");
                 let userdata = Userdata::default();
                 self.save(&userdata).await?;
                 Ok(userdata)
@@ -76,4 +136,51 @@ impl<'a> UserdataIo<'a> {
 
         Ok(())
     }
+
+    /// Find latest valid usage record and next slot to write.
+    /// Slots corrupted by power loss are skipped.
+    pub async fn init_usage(&mut self) -> Option<UsageStats> {
+        let mut latest = None;
+        self.usage_slot = 0;
+
+        for slot in 0..USAGE_SLOTS {
+            let mut buf = [0_u32; { size_of::<UsageRecord>() / 4 }];
+            self.flash
+                .background_read(
+                    (usage_start_offset() + slot * size_of::<UsageRecord>()) as _,
+                    &mut buf,
+                )
+                .ok()?
+                .await;
+
+            let record = UsageRecord::read_from_bytes(buf.as_bytes()).ok()?;
+            if record.erased() {
+                continue;
+            }
+
+            self.usage_slot = slot + 1;
+            if record.valid() {
+                latest = Some(record.stats);
+            }
+        }
+
+        latest
+    }
+
+    /// Append [`UsageStats`] record.
+    /// The sector is erased once every [`USAGE_SLOTS`] saves.
+    pub async fn save_usage(&mut self, stats: &UsageStats) -> Result<(), flash::Error> {
+        if self.usage_slot >= USAGE_SLOTS {
+            self.flash.blocking_erase(
+                usage_start_offset() as _,
+                usage_start_offset() as u32 + ERASE_SIZE as u32,
+            )?;
+            self.usage_slot = 0;
+        }
+
+        let offset = usage_start_offset() + self.usage_slot * size_of::<UsageRecord>();
+        self.usage_slot += 1;
+        self.flash
+            .blocking_write(offset as _, UsageRecord::new(*stats).as_bytes())
+    }
 }
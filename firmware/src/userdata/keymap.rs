@@ -1,7 +1,10 @@
 use keycode::Keycode;
 use zerocopy::{Immutable, IntoBytes, TryFromBytes};
 
-use crate::keycodes;
+use crate::{input::reader::button::BUTTON_COUNT, keycodes};
+
+/// Keymap layers. Layer 0 is the base layer
+pub const LAYER_COUNT: usize = 4;
 
 #[derive(Clone, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
 #[repr(C)]
@@ -22,6 +25,11 @@ pub struct Keymap {
     pub fx1: Keycode,
     pub fx2: Keycode,
 
+    pub aux1: Keycode,
+    pub aux2: Keycode,
+    pub aux3: Keycode,
+    pub aux4: Keycode,
+
     pub _unused: u16,
 }
 
@@ -38,8 +46,58 @@ impl Keymap {
         button4: keycodes::JOY_BTN8,
         fx1: keycodes::DPAD_DOWN,
         fx2: keycodes::JOY_BTN2,
+        aux1: Keycode::KC_ESCAPE,
+        aux2: keycodes::EAC_SERVICE,
+        aux3: keycodes::EAC_TEST,
+        aux4: Keycode::KC_NO,
+        _unused: 0,
+    };
+}
+
+impl Keymap {
+    /// Upper layer falling through to lower layers
+    pub const TRANSPARENT: Self = Self {
+        left_knob_left: Keycode::KC_TRANSPARENT,
+        left_knob_right: Keycode::KC_TRANSPARENT,
+        start: Keycode::KC_TRANSPARENT,
+        right_knob_left: Keycode::KC_TRANSPARENT,
+        right_knob_right: Keycode::KC_TRANSPARENT,
+        button1: Keycode::KC_TRANSPARENT,
+        button2: Keycode::KC_TRANSPARENT,
+        button3: Keycode::KC_TRANSPARENT,
+        button4: Keycode::KC_TRANSPARENT,
+        fx1: Keycode::KC_TRANSPARENT,
+        fx2: Keycode::KC_TRANSPARENT,
+        aux1: Keycode::KC_TRANSPARENT,
+        aux2: Keycode::KC_TRANSPARENT,
+        aux3: Keycode::KC_TRANSPARENT,
+        aux4: Keycode::KC_TRANSPARENT,
         _unused: 0,
     };
+
+    pub const DEFAULT_LAYERS: [Self; LAYER_COUNT] = [
+        Self::DEFAULT,
+        Self::TRANSPARENT,
+        Self::TRANSPARENT,
+        Self::TRANSPARENT,
+    ];
+
+    /// Button keycodes in [`ButtonId`](crate::input::reader::button::ButtonId) order
+    pub const fn buttons(&self) -> [Keycode; BUTTON_COUNT] {
+        [
+            self.button1,
+            self.button2,
+            self.button3,
+            self.button4,
+            self.fx1,
+            self.fx2,
+            self.start,
+            self.aux1,
+            self.aux2,
+            self.aux3,
+            self.aux4,
+        ]
+    }
 }
 
 impl Default for Keymap {
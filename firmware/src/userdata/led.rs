@@ -0,0 +1,32 @@
+use zerocopy::{Immutable, IntoBytes, TryFromBytes};
+
+/// LED lighting effect
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(u8)]
+pub enum LedEffect {
+    /// Lamps are lit only while active
+    Static = 0,
+    /// Idle lamps slowly fade in and out
+    Breathing = 1,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct LedSettings {
+    /// Lamp brightness (0-255)
+    pub brightness: u8,
+    pub effect: LedEffect,
+}
+
+impl LedSettings {
+    pub const DEFAULT: Self = Self {
+        brightness: 255,
+        effect: LedEffect::Static,
+    };
+}
+
+impl Default for LedSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
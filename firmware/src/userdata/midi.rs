@@ -0,0 +1,44 @@
+use zerocopy::{Immutable, IntoBytes, TryFromBytes};
+
+/// CC message type sent by knobs
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(u8)]
+pub enum MidiKnobMode {
+    /// Knob position (0-127), wrapping around on every turn
+    Absolute = 0,
+    /// Turned amount as offset from 64 (65+: clockwise, 63-: counter clockwise)
+    Relative = 1,
+}
+
+/// MIDI mode settings
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct MidiSettings {
+    /// MIDI channel (0-15)
+    pub channel: u8,
+    pub knob_mode: MidiKnobMode,
+    /// CC number for left knob
+    pub left_knob_cc: u8,
+    /// CC number for right knob
+    pub right_knob_cc: u8,
+    /// Note numbers of BT-A, BT-B, BT-C, BT-D, FX-L, FX-R, Start
+    pub notes: [u8; 7],
+}
+
+impl MidiSettings {
+    pub const DEFAULT: Self = Self {
+        channel: 0,
+        knob_mode: MidiKnobMode::Relative,
+        // General purpose controllers
+        left_knob_cc: 16,
+        right_knob_cc: 17,
+        // C4 to F#4
+        notes: [60, 61, 62, 63, 64, 65, 66],
+    };
+}
+
+impl Default for MidiSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
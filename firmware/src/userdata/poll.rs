@@ -0,0 +1,45 @@
+use zerocopy::{Immutable, IntoBytes, TryFromBytes};
+
+type PollField = fn(&mut PollIntervals) -> &mut u8;
+
+/// USB polling intervals of interfaces in milliseconds (1-255)
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct PollIntervals {
+    pub eac: u8,
+    pub gamepad: u8,
+    pub keyboard: u8,
+    pub mouse: u8,
+    pub switch: u8,
+    pub xinput: u8,
+    pub via: u8,
+}
+
+impl PollIntervals {
+    pub const DEFAULT: Self = Self {
+        eac: 1,
+        gamepad: 1,
+        keyboard: 1,
+        mouse: 1,
+        switch: 1,
+        xinput: 1,
+        via: 8,
+    };
+
+    /// Interface names and fields
+    pub const FIELDS: [(&str, PollField); 7] = [
+        ("eac", |poll| &mut poll.eac),
+        ("gamepad", |poll| &mut poll.gamepad),
+        ("keyboard", |poll| &mut poll.keyboard),
+        ("mouse", |poll| &mut poll.mouse),
+        ("switch", |poll| &mut poll.switch),
+        ("xinput", |poll| &mut poll.xinput),
+        ("via", |poll| &mut poll.via),
+    ];
+}
+
+impl Default for PollIntervals {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,34 @@
+use zerocopy::{Immutable, IntoBytes, TryFromBytes};
+
+/// Gamepad thumbstick axis
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable, Debug, defmt::Format)]
+#[repr(u8)]
+pub enum StickAxis {
+    LeftX = 0,
+    LeftY = 1,
+    RightX = 2,
+    RightY = 3,
+}
+
+/// Thumbstick axes of knobs in gamepad modes (XInput, Switch)
+#[derive(Clone, Copy, PartialEq, Eq, TryFromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct KnobAxes {
+    /// Thumbstick axis for left knob
+    pub left_knob: StickAxis,
+    /// Thumbstick axis for right knob
+    pub right_knob: StickAxis,
+}
+
+impl KnobAxes {
+    pub const DEFAULT: Self = Self {
+        left_knob: StickAxis::LeftX,
+        right_knob: StickAxis::LeftY,
+    };
+}
+
+impl Default for KnobAxes {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,81 @@
+//! Usage statistics for estimating switch wear.
+//! Counted in RAM and appended to the usage flash sector periodically, see [`UserdataIo::save_usage`](super::io::UserdataIo::save_usage).
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use portable_atomic::{AtomicBool, Ordering};
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+#[derive(Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
+#[repr(C)]
+pub struct UsageStats {
+    /// Press counts of main buttons in [`ButtonId`](crate::input::reader::button::ButtonId) order.
+    /// Auxiliary buttons are not counted.
+    pub presses: [u32; 7],
+    /// Travel of left and right knob in ADC steps
+    pub knob_travel: [u32; 2],
+}
+
+impl UsageStats {
+    pub const DEFAULT: Self = Self {
+        presses: [0; 7],
+        knob_travel: [0; 2],
+    };
+
+    /// Knob travel of a full turn
+    pub const KNOB_TURN: u32 = 4096;
+}
+
+impl Default for UsageStats {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static USAGE: Mutex<CriticalSectionRawMutex, RefCell<UsageStats>> =
+    Mutex::new(RefCell::new(UsageStats::DEFAULT));
+/// Changed since last save
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// Get current [`UsageStats`]
+pub fn get() -> UsageStats {
+    USAGE.lock(|usage| *usage.borrow())
+}
+
+/// Count presses of buttons in `pressed` mask and knob travel
+pub fn count(pressed: u16, knob_travel: [u32; 2]) {
+    if pressed == 0 && knob_travel == [0; 2] {
+        return;
+    }
+
+    USAGE.lock(|usage| {
+        let mut usage = usage.borrow_mut();
+        for (i, presses) in usage.presses.iter_mut().enumerate() {
+            if pressed & (1 << i) != 0 {
+                *presses = presses.saturating_add(1);
+            }
+        }
+
+        for (travel, add) in usage.knob_travel.iter_mut().zip(knob_travel) {
+            *travel = travel.saturating_add(add);
+        }
+    });
+    DIRTY.store(true, Ordering::Relaxed);
+}
+
+/// Reset all counters
+pub fn reset() {
+    USAGE.lock(|usage| *usage.borrow_mut() = UsageStats::DEFAULT);
+    DIRTY.store(true, Ordering::Relaxed);
+}
+
+/// Set counters loaded from flash
+pub(super) fn load(stats: UsageStats) {
+    USAGE.lock(|usage| *usage.borrow_mut() = stats);
+}
+
+/// Get counters if changed since last call
+pub(super) fn take_changed() -> Option<UsageStats> {
+    DIRTY.swap(false, Ordering::Relaxed).then(get)
+}
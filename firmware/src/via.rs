@@ -3,6 +3,8 @@ mod custom;
 mod encoder;
 mod keyboard;
 mod keymap;
+mod lighting;
+mod vial;
 
 use embassy_executor::SpawnToken;
 use embassy_usb::class::hid::{HidReaderWriter, State};
@@ -11,8 +13,12 @@ use static_cell::StaticCell;
 use zerocopy::{FromBytes, IntoBytes, big_endian};
 
 use crate::{
+    log,
     usb::{self, Driver, hid::QmkRawHidReport},
-    userdata::{self, keymap::Keymap},
+    userdata::{
+        self,
+        keymap::{Keymap, LAYER_COUNT},
+    },
     via::{
         cmds::*,
         encoder::{get_encoder_keycode, set_encoder_keycode},
@@ -20,6 +26,9 @@ use crate::{
     },
 };
 
+/// LZMA compressed Via definition generated by build script
+static DEFINITION: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/via_definition.lzma"));
+
 pub fn via_task(
     builder: &mut embassy_usb::Builder<'static, Driver>,
 ) -> SpawnToken<impl Sized + use<>> {
@@ -31,19 +40,19 @@ pub fn via_task(
         let mut buf = [0_u8; { size_of::<QmkRawHidReport>() }];
         loop {
             if let Err(e) = reader.read(&mut buf).await {
-                defmt::error!("Failed to send via report err:{:?}", e);
+                log::error!("Failed to send via report err:{:?}", e);
                 continue;
             }
 
             let Some(cmd) = ViaCmd::from_raw(&mut buf) else {
-                defmt::error!("Failed parse via report");
+                log::error!("Failed parse via report");
                 continue;
             };
 
             cmd.invoke().await;
 
             if let Err(err) = writer.write(&buf).await {
-                defmt::error!("Failed to write via report. err: {:?}", err);
+                log::error!("Failed to write via report. err: {:?}", err);
             }
         }
     }
@@ -78,6 +87,7 @@ impl ViaCmdId {
     pub const DYNAMIC_KEYMAP_SET_BUFFER: u8 = 0x13;
     pub const DYNAMIC_KEYMAP_GET_ENCODER: u8 = 0x14;
     pub const DYNAMIC_KEYMAP_SET_ENCODER: u8 = 0x15;
+    pub const VIAL_PREFIX: u8 = 0xFE;
     pub const UNHANDLED: u8 = 0xff;
 }
 
@@ -105,7 +115,7 @@ impl<'a> ViaCmd<'a> {
                     .unwrap()
                     .0
                     .version = GetProtocolVersion::CURRENT_VERSION.into();
-                defmt::info!("Via connected.");
+                log::info!("Via connected.");
             }
 
             ViaCmdId::GET_KEYBOARD_VALUE => {
@@ -116,7 +126,8 @@ impl<'a> ViaCmd<'a> {
                 let cmd = DynamicKeymapKeycode::mut_from_prefix(self.data).unwrap().0;
 
                 let key = userdata::get(|userdata| {
-                    get_keymap_keycode(&userdata.keymap, cmd.row, cmd.col)
+                    let keymap = userdata.keymaps.get(cmd.layer as usize)?;
+                    get_keymap_keycode(keymap, cmd.row, cmd.col)
                 })
                 .unwrap_or_default();
                 cmd.key = big_endian::U16::new(key.0);
@@ -127,11 +138,14 @@ impl<'a> ViaCmd<'a> {
                 let key = Keycode::from(cmd.key.get());
 
                 userdata::update(|userdata| {
-                    set_keymap_keycode(&mut userdata.keymap, cmd.row, cmd.col, key);
+                    if let Some(keymap) = userdata.keymaps.get_mut(cmd.layer as usize) {
+                        set_keymap_keycode(keymap, cmd.row, cmd.col, key);
+                    }
                 });
                 userdata::save();
-                defmt::info!(
-                    "Keycode at row: {} col: {} updated to key: {:#06X}",
+                log::info!(
+                    "Keycode at layer: {} row: {} col: {} updated to key: {:#06X}",
+                    cmd.layer,
                     cmd.row,
                     cmd.col,
                     key.0
@@ -140,9 +154,9 @@ impl<'a> ViaCmd<'a> {
 
             ViaCmdId::DYNAMIC_KEYMAP_RESET => {
                 userdata::update(|userdata| {
-                    userdata.keymap = Keymap::DEFAULT;
+                    userdata.keymaps = Keymap::DEFAULT_LAYERS;
                 });
-                defmt::info!("Keymap resetted to default.");
+                log::info!("Keymap resetted to default.");
             }
 
             ViaCmdId::CUSTOM_GET_VALUE => {
@@ -168,8 +182,7 @@ impl<'a> ViaCmd<'a> {
             }
 
             ViaCmdId::DYNAMIC_KEYMAP_GET_LAYER_COUNT => {
-                // Hardcode layer count 1
-                self.data[0] = 1;
+                self.data[0] = LAYER_COUNT as u8;
             }
 
             ViaCmdId::DYNAMIC_KEYMAP_GET_BUFFER => {
@@ -178,7 +191,7 @@ impl<'a> ViaCmd<'a> {
                 let size = cmd.size as usize;
 
                 let keymap_buf =
-                    userdata::get(|userdata| KeymapBuffer::from_keymap(&userdata.keymap));
+                    userdata::get(|userdata| KeymapBuffer::from_keymaps(&userdata.keymaps));
 
                 let Some(src) = keymap_buf.as_bytes().get(offset..(offset + size)) else {
                     self.set_invalid();
@@ -197,7 +210,7 @@ impl<'a> ViaCmd<'a> {
                 let size = cmd.size as usize;
 
                 let mut keymap_buf =
-                    userdata::get(|userdata| KeymapBuffer::from_keymap(&userdata.keymap));
+                    userdata::get(|userdata| KeymapBuffer::from_keymaps(&userdata.keymaps));
 
                 let Some(dst) = keymap_buf.as_mut_bytes().get_mut(offset..(offset + size)) else {
                     self.set_invalid();
@@ -209,15 +222,19 @@ impl<'a> ViaCmd<'a> {
                 };
                 dst.copy_from_slice(src);
 
-                userdata::update(|userdata| keymap_buf.apply_keymap(&mut userdata.keymap));
+                userdata::update(|userdata| {
+                    for (buf, keymap) in keymap_buf.iter().zip(&mut userdata.keymaps) {
+                        buf.apply_keymap(keymap);
+                    }
+                });
                 userdata::save();
             }
 
             ViaCmdId::DYNAMIC_KEYMAP_GET_ENCODER => {
                 let cmd = DynamicKeymapEncoder::mut_from_prefix(self.data).unwrap().0;
 
-                let key =
-                    get_encoder_keycode(cmd.encoder_id, cmd.clockwise != 0).unwrap_or_default();
+                let key = get_encoder_keycode(cmd.layer, cmd.encoder_id, cmd.clockwise != 0)
+                    .unwrap_or_default();
                 cmd.key = big_endian::U16::new(key.0);
             }
 
@@ -225,12 +242,16 @@ impl<'a> ViaCmd<'a> {
                 let cmd = DynamicKeymapEncoder::mut_from_prefix(self.data).unwrap().0;
 
                 let key = Keycode::from(cmd.key.get());
-                set_encoder_keycode(cmd.encoder_id, cmd.clockwise != 0, key);
+                set_encoder_keycode(cmd.layer, cmd.encoder_id, cmd.clockwise != 0, key);
                 userdata::save();
             }
 
+            ViaCmdId::VIAL_PREFIX => {
+                self.read_vial_cmd();
+            }
+
             _ => {
-                defmt::warn!("Invalid via command recevied: {:#04X}", *self.id);
+                log::warn!("Invalid via command recevied: {:#04X}", *self.id);
                 self.set_invalid();
             }
         }
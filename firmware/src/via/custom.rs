@@ -1,68 +1,308 @@
-use embassy_rp::rom_data;
-
 use crate::{
-    userdata::{self},
-    via::ViaCmd,
+    chip,
+    input::latency,
+    led, log,
+    userdata::{
+        self, LogFormat, UsbMode,
+        midi::{MidiKnobMode, MidiSettings},
+        poll::PollIntervals,
+        stick::{KnobAxes, StickAxis},
+    },
+    via::{ViaCmd, vial},
 };
 
+/// Via custom value channel ids from
+/// https://github.com/qmk/qmk_firmware/blob/acbeec29dab5331fe914f35a53d6b43325881e4d/quantum/via.h#L98
+struct ChannelId;
+impl ChannelId {
+    /// Custom user defined channel
+    pub const CUSTOM: u8 = 0x00;
+    pub const QMK_BACKLIGHT: u8 = 0x01;
+}
+
 struct ValueId;
 impl ValueId {
     /// Reboot to BOOTSEL
     pub const REBOOT_BOOTSEL: u8 = 0x02;
-    /// SDVX EAC Mode
-    pub const EAC_MODE: u8 = 0x03;
+    /// USB Mode
+    pub const USB_MODE: u8 = 0x03;
+    /// LED diagnostics
+    pub const LED_DIAGNOSTICS: u8 = 0x04;
+    /// Gamepad stick axis for left knob
+    pub const LEFT_KNOB_AXIS: u8 = 0x06;
+    /// Gamepad stick axis for right knob
+    pub const RIGHT_KNOB_AXIS: u8 = 0x07;
+    /// Combo window in ms, 2 bytes big endian
+    pub const COMBO_TERM: u8 = 0x08;
+    /// MIDI channel
+    pub const MIDI_CHANNEL: u8 = 0x09;
+    /// MIDI knob CC mode
+    pub const MIDI_KNOB_MODE: u8 = 0x0A;
+    /// MIDI CC number for left knob
+    pub const MIDI_LEFT_KNOB_CC: u8 = 0x0B;
+    /// MIDI CC number for right knob
+    pub const MIDI_RIGHT_KNOB_CC: u8 = 0x0C;
+    /// Log format
+    pub const LOG_FORMAT: u8 = 0x0D;
+    /// Latency measurement
+    pub const LATENCY_MEASUREMENT: u8 = 0x0E;
+    /// Tapping term of dual-role keys in ms, 2 bytes big endian
+    pub const TAPPING_TERM: u8 = 0x0F;
+    /// MIDI note numbers from BT-A to Start (0x10-0x16)
+    pub const MIDI_NOTE_START: u8 = 0x10;
+    pub const MIDI_NOTE_END: u8 = 0x16;
+    /// USB polling intervals in [`PollIntervals::FIELDS`] order (0x20-0x26)
+    pub const POLL_START: u8 = 0x20;
+    pub const POLL_END: u8 = 0x26;
 }
 
 impl ViaCmd<'_> {
     pub fn read_custom_get_value(self) {
-        let channel_id = self.data[0];
+        match self.data[0] {
+            ChannelId::CUSTOM => self.read_user_get_value(),
+            ChannelId::QMK_BACKLIGHT => self.read_backlight_get_value(),
+            _ => self.set_invalid(),
+        }
+    }
 
-        // 0 for custom user defined channel
-        // We will only use user defined channel so ignore rest
-        if channel_id != 0 {
-            self.set_invalid();
-            return;
+    pub fn read_custom_set_value(self) {
+        match self.data[0] {
+            ChannelId::CUSTOM => self.read_user_set_value(),
+            ChannelId::QMK_BACKLIGHT => self.read_backlight_set_value(),
+            _ => self.set_invalid(),
+        }
+    }
+
+    pub fn read_custom_save(self) {
+        match self.data[0] {
+            ChannelId::CUSTOM | ChannelId::QMK_BACKLIGHT => {
+                userdata::save();
+            }
+
+            _ => self.set_invalid(),
         }
+    }
 
+    fn read_user_get_value(self) {
         let value_id = self.data[1];
         match value_id {
-            ValueId::REBOOT_BOOTSEL | ValueId::EAC_MODE => {
+            ValueId::REBOOT_BOOTSEL => {
                 // Fixed value
                 self.data[2] = 1;
             }
 
+            ValueId::USB_MODE => {
+                self.data[2] = userdata::get(|data| data.mode) as u8;
+            }
+
+            ValueId::LED_DIAGNOSTICS => {
+                self.data[2] = led::diagnostics_running() as u8;
+            }
+
+            ValueId::LATENCY_MEASUREMENT => {
+                self.data[2] = latency::enabled() as u8;
+            }
+
+            ValueId::COMBO_TERM => {
+                let term = userdata::get(|data| data.combo_term_ms);
+                self.data[2..4].copy_from_slice(&term.to_be_bytes());
+            }
+
+            ValueId::TAPPING_TERM => {
+                let term = userdata::get(|data| data.tapping_term_ms);
+                self.data[2..4].copy_from_slice(&term.to_be_bytes());
+            }
+
+            ValueId::LEFT_KNOB_AXIS => {
+                self.data[2] = userdata::get(|data| data.knob_axes.left_knob) as u8;
+            }
+
+            ValueId::RIGHT_KNOB_AXIS => {
+                self.data[2] = userdata::get(|data| data.knob_axes.right_knob) as u8;
+            }
+
+            ValueId::MIDI_CHANNEL => {
+                self.data[2] = userdata::get(|data| data.midi.channel);
+            }
+
+            ValueId::LOG_FORMAT => {
+                self.data[2] = userdata::get(|data| data.log_format) as u8;
+            }
+
+            ValueId::POLL_START..=ValueId::POLL_END => {
+                let (_, field) = PollIntervals::FIELDS[(value_id - ValueId::POLL_START) as usize];
+                let mut poll = userdata::get(|data| data.poll);
+                self.data[2] = *field(&mut poll);
+            }
+
+            ValueId::MIDI_KNOB_MODE => {
+                self.data[2] = userdata::get(|data| data.midi.knob_mode) as u8;
+            }
+
+            ValueId::MIDI_LEFT_KNOB_CC => {
+                self.data[2] = userdata::get(|data| data.midi.left_knob_cc);
+            }
+
+            ValueId::MIDI_RIGHT_KNOB_CC => {
+                self.data[2] = userdata::get(|data| data.midi.right_knob_cc);
+            }
+
+            ValueId::MIDI_NOTE_START..=ValueId::MIDI_NOTE_END => {
+                let index = (value_id - ValueId::MIDI_NOTE_START) as usize;
+                self.data[2] = userdata::get(|data| data.midi.notes[index]);
+            }
+
             _ => {
                 self.set_invalid();
             }
         }
     }
 
-    pub fn read_custom_set_value(self) {
-        let channel_id = self.data[0];
-
-        // 0 for custom user defined channel
-        // We will only use user defined channel so ignore rest
-        if channel_id != 0 {
-            self.set_invalid();
-            return;
-        }
-
+    fn read_user_set_value(self) {
         let value_id = self.data[1];
         match value_id {
             ValueId::REBOOT_BOOTSEL => {
-                defmt::info!("BOOTSEL Reboot requested.");
+                if !vial::unlocked() {
+                    log::warn!("BOOTSEL Reboot rejected. Keyboard is locked.");
+                    self.set_invalid();
+                    return;
+                }
+
+                log::info!("BOOTSEL Reboot requested.");
                 // Reboot to BOOTSEL
-                rom_data::reset_to_usb_boot(0, 0);
+                chip::reboot_to_bootsel();
             }
 
-            ValueId::EAC_MODE => {
-                defmt::info!("EAC Mode enabled.");
+            ValueId::USB_MODE => {
+                let mode = match self.data[2] {
+                    0 => UsbMode::Hid,
+                    1 => UsbMode::Eac,
+                    2 => UsbMode::XInput,
+                    3 => UsbMode::Switch,
+                    4 => UsbMode::Midi,
+                    _ => {
+                        self.set_invalid();
+                        return;
+                    }
+                };
+
+                if userdata::get(|data| data.mode) == mode {
+                    return;
+                }
+                log::info!("{:?} mode enabled.", mode);
                 userdata::update(|data| {
-                    data.eac_mode = true;
+                    data.mode = mode;
                 });
-                userdata::save();
+                userdata::save_and_reboot();
+            }
+
+            ValueId::LEFT_KNOB_AXIS => {
+                self.set_knob_axis(|axes| &mut axes.left_knob);
+            }
+
+            ValueId::RIGHT_KNOB_AXIS => {
+                self.set_knob_axis(|axes| &mut axes.right_knob);
+            }
+
+            ValueId::MIDI_CHANNEL => {
+                self.set_midi_value(0x0F, |midi| &mut midi.channel);
+            }
+
+            ValueId::LOG_FORMAT => {
+                let format = match self.data[2] {
+                    0 => LogFormat::Defmt,
+                    1 => LogFormat::Text,
+                    _ => {
+                        self.set_invalid();
+                        return;
+                    }
+                };
+
+                userdata::update(|data| {
+                    data.log_format = format;
+                });
+            }
+
+            ValueId::POLL_START..=ValueId::POLL_END => {
+                let ms = self.data[2];
+                if ms == 0 {
+                    self.set_invalid();
+                    return;
+                }
+
+                let (_, field) = PollIntervals::FIELDS[(value_id - ValueId::POLL_START) as usize];
+                userdata::update(|data| {
+                    *field(&mut data.poll) = ms;
+                });
+            }
+
+            ValueId::MIDI_KNOB_MODE => {
+                let knob_mode = match self.data[2] {
+                    0 => MidiKnobMode::Absolute,
+                    1 => MidiKnobMode::Relative,
+                    _ => {
+                        self.set_invalid();
+                        return;
+                    }
+                };
 
-                rom_data::reboot(0, 1, 0, 0);
+                userdata::update(|data| {
+                    data.midi.knob_mode = knob_mode;
+                });
+            }
+
+            ValueId::MIDI_LEFT_KNOB_CC => {
+                self.set_midi_value(0x7F, |midi| &mut midi.left_knob_cc);
+            }
+
+            ValueId::MIDI_RIGHT_KNOB_CC => {
+                self.set_midi_value(0x7F, |midi| &mut midi.right_knob_cc);
+            }
+
+            ValueId::MIDI_NOTE_START..=ValueId::MIDI_NOTE_END => {
+                let index = (value_id - ValueId::MIDI_NOTE_START) as usize;
+                self.set_midi_value(0x7F, |midi| &mut midi.notes[index]);
+            }
+
+            ValueId::LATENCY_MEASUREMENT => {
+                if self.data[2] != 0 {
+                    log::info!("Latency measurement started.");
+                    latency::reset();
+                    latency::set_enabled(true);
+                } else {
+                    log::info!("Latency measurement stopped.");
+                    latency::set_enabled(false);
+                }
+            }
+
+            ValueId::COMBO_TERM => {
+                let term = u16::from_be_bytes([self.data[2], self.data[3]]);
+                if term == 0 {
+                    self.set_invalid();
+                    return;
+                }
+
+                userdata::update(|data| data.combo_term_ms = term);
+            }
+
+            ValueId::TAPPING_TERM => {
+                let term = u16::from_be_bytes([self.data[2], self.data[3]]);
+                if term == 0 {
+                    self.set_invalid();
+                    return;
+                }
+
+                userdata::update(|data| data.tapping_term_ms = term);
+            }
+
+            ValueId::LED_DIAGNOSTICS => {
+                if self.data[2] != 0 {
+                    log::info!("LED diagnostics started.");
+                    led::start_diagnostics();
+                } else {
+                    log::info!("LED diagnostics stopped.");
+                    led::stop_diagnostics();
+                }
             }
 
             _ => {
@@ -71,16 +311,33 @@ impl ViaCmd<'_> {
         }
     }
 
-    pub fn read_custom_save(self) {
-        let channel_id = self.data[0];
+    fn set_knob_axis(self, f: impl FnOnce(&mut KnobAxes) -> &mut StickAxis) {
+        let axis = match self.data[2] {
+            0 => StickAxis::LeftX,
+            1 => StickAxis::LeftY,
+            2 => StickAxis::RightX,
+            3 => StickAxis::RightY,
+            _ => {
+                self.set_invalid();
+                return;
+            }
+        };
+
+        userdata::update(|data| {
+            *f(&mut data.knob_axes) = axis;
+        });
+        log::info!("Knob axis set to {:?}", axis);
+    }
 
-        // 0 for custom user defined channel
-        // We will only use user defined channel so ignore rest
-        if channel_id != 0 {
+    fn set_midi_value(self, max: u8, f: impl FnOnce(&mut MidiSettings) -> &mut u8) {
+        let value = self.data[2];
+        if value > max {
             self.set_invalid();
             return;
         }
 
-        userdata::save();
+        userdata::update(|data| {
+            *f(&mut data.midi) = value;
+        });
     }
 }
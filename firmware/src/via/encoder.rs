@@ -2,34 +2,43 @@ use keycode::Keycode;
 
 use crate::userdata;
 
-pub fn get_encoder_keycode(id: u8, clockwise: bool) -> Option<Keycode> {
-    userdata::get(|data| match (id, clockwise) {
-        (1, false) => Some(data.keymap.left_knob_left),
-        (1, true) => Some(data.keymap.left_knob_right),
+pub fn get_encoder_keycode(layer: u8, id: u8, clockwise: bool) -> Option<Keycode> {
+    userdata::get(|data| {
+        let keymap = data.keymaps.get(layer as usize)?;
+        match (id, clockwise) {
+            (1, false) => Some(keymap.left_knob_left),
+            (1, true) => Some(keymap.left_knob_right),
 
-        (2, false) => Some(data.keymap.right_knob_left),
-        (2, true) => Some(data.keymap.right_knob_right),
+            (2, false) => Some(keymap.right_knob_left),
+            (2, true) => Some(keymap.right_knob_right),
 
-        _ => None,
+            _ => None,
+        }
     })
 }
 
-pub fn set_encoder_keycode(id: u8, clockwise: bool, code: Keycode) {
-    userdata::update(|data| match (id, clockwise) {
-        (1, false) => {
-            data.keymap.left_knob_left = code;
-        }
-        (1, true) => {
-            data.keymap.left_knob_right = code;
-        }
+pub fn set_encoder_keycode(layer: u8, id: u8, clockwise: bool, code: Keycode) {
+    userdata::update(|data| {
+        let Some(keymap) = data.keymaps.get_mut(layer as usize) else {
+            return;
+        };
 
-        (2, false) => {
-            data.keymap.right_knob_left = code;
-        }
-        (2, true) => {
-            data.keymap.right_knob_right = code;
-        }
+        match (id, clockwise) {
+            (1, false) => {
+                keymap.left_knob_left = code;
+            }
+            (1, true) => {
+                keymap.left_knob_right = code;
+            }
+
+            (2, false) => {
+                keymap.right_knob_left = code;
+            }
+            (2, true) => {
+                keymap.right_knob_right = code;
+            }
 
-        _ => {}
+            _ => {}
+        }
     })
 }
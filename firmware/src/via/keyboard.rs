@@ -1,6 +1,17 @@
 use embassy_time::Instant;
 
-use crate::{input::CURRENT_INPUT, via::ViaCmd};
+use crate::{
+    crash,
+    input::{
+        CURRENT_INPUT, chatter,
+        latency::{self, LatencyStage},
+        reader::button::ButtonId,
+        report::REPORT_STATS,
+    },
+    log,
+    userdata::usage,
+    via::{DEFINITION, ViaCmd, vial},
+};
 
 /// Via keyboard value id from
 /// https://github.com/qmk/qmk_firmware/blob/acbeec29dab5331fe914f35a53d6b43325881e4d/quantum/via.h#L79
@@ -10,6 +21,21 @@ impl ViaKeyboardValueId {
     pub const SWITCH_MATRIX_STATE: u8 = 0x03;
     pub const FIRMWARE_VERSION: u8 = 0x04;
     pub const DEVICE_INDICATION: u8 = 0x05;
+    /// Vendor specific. Report counters of interface at index in `data[1]`
+    pub const REPORT_STATS: u8 = 0x80;
+    /// Vendor specific. Latency summary in us of stage at index in `data[1]`
+    pub const LATENCY: u8 = 0x81;
+    /// Vendor specific. Last crash reason text from byte offset in `data[1]`.
+    /// `data[2]` is total text length, 0 if no crash is recorded
+    pub const CRASH: u8 = 0x82;
+    /// Vendor specific. Usage counter at index in `data[1]`.
+    /// BT-A, BT-B, BT-C, BT-D, FX-L, FX-R, Start press counts, then left and right knob travel in ADC steps (4096 per turn)
+    pub const USAGE: u8 = 0x83;
+    /// Vendor specific. Chatter statistics of button at index in `data[1]`, in [`Self::USAGE`] button order
+    pub const CHATTER: u8 = 0x84;
+    /// Vendor specific. LZMA compressed Via definition from byte offset in `data[1..3]`.
+    /// `data[3..5]` is total length
+    pub const DEFINITION: u8 = 0x85;
 }
 
 /// Value extracted from
@@ -31,16 +57,27 @@ impl ViaCmd<'_> {
             }
 
             ViaKeyboardValueId::SWITCH_MATRIX_STATE => {
+                // Matrix tester requires unlock in Vial
+                if !vial::unlocked() {
+                    self.set_invalid();
+                    return;
+                }
+
                 let offset = self.data[1];
 
                 let read = CURRENT_INPUT.borrow().get();
+                let level = |button| read.buttons.level(button) as u8;
                 let matrix = [
-                    (read.buttons.start as u8) << 1,
-                    (read.buttons.button1 as u8)
-                        | (read.buttons.button2 as u8) << 1
-                        | (read.buttons.button3 as u8) << 2
-                        | (read.buttons.button4 as u8) << 3,
-                    (read.buttons.fx1 as u8) | (read.buttons.fx2 as u8) << 1,
+                    level(ButtonId::START) << 1,
+                    level(ButtonId::BUTTON1)
+                        | level(ButtonId::BUTTON2) << 1
+                        | level(ButtonId::BUTTON3) << 2
+                        | level(ButtonId::BUTTON4) << 3,
+                    level(ButtonId::FX1) | level(ButtonId::FX2) << 1,
+                    level(ButtonId::AUX1)
+                        | level(ButtonId::AUX2) << 1
+                        | level(ButtonId::AUX3) << 2
+                        | level(ButtonId::AUX4) << 3,
                 ];
 
                 if let Some(matrix_slice) = matrix.get((offset as usize)..) {
@@ -54,12 +91,103 @@ impl ViaCmd<'_> {
                     return;
                 }
 
-                defmt::info!("Via device ACK");
+                log::info!("Via device ACK");
+            }
+
+            ViaKeyboardValueId::REPORT_STATS => {
+                let Some((_, stats)) = REPORT_STATS.get(self.data[1] as usize) else {
+                    self.set_invalid();
+                    return;
+                };
+
+                let counts = stats.counts();
+                self.data[2..6].copy_from_slice(&counts.sent.to_be_bytes());
+                self.data[6..10].copy_from_slice(&counts.dropped.to_be_bytes());
+                self.data[10..14].copy_from_slice(&counts.errors.to_be_bytes());
+            }
+
+            ViaKeyboardValueId::LATENCY => {
+                let Some((_, stage)) = LatencyStage::ALL.get(self.data[1] as usize) else {
+                    self.set_invalid();
+                    return;
+                };
+
+                let summary = latency::summary(*stage);
+                for (i, value) in [
+                    summary.count,
+                    summary.min,
+                    summary.avg,
+                    summary.max,
+                    summary.p99,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    self.data[2 + i * 4..][..4].copy_from_slice(&value.to_be_bytes());
+                }
+            }
+
+            ViaKeyboardValueId::CRASH => {
+                let offset = self.data[1] as usize;
+                let Some(text) = crash::last() else {
+                    self.data[2] = 0;
+                    return;
+                };
+
+                self.data[2] = text.len() as u8;
+                if let Some(chunk) = text.as_bytes().get(offset..) {
+                    let len = chunk.len().min(self.data.len() - 3);
+                    self.data[3..][..len].copy_from_slice(&chunk[..len]);
+                }
+            }
+
+            ViaKeyboardValueId::USAGE => {
+                let stats = usage::get();
+                let Some(value) = stats
+                    .presses
+                    .iter()
+                    .chain(&stats.knob_travel)
+                    .nth(self.data[1] as usize)
+                else {
+                    self.set_invalid();
+                    return;
+                };
+
+                self.data[2..6].copy_from_slice(&value.to_be_bytes());
+            }
+
+            ViaKeyboardValueId::CHATTER => {
+                let Some(stats) = chatter::stats(self.data[1] as usize) else {
+                    self.set_invalid();
+                    return;
+                };
+
+                for (i, value) in [
+                    stats.releases,
+                    stats.bounces,
+                    stats.max_duration_ms as u32,
+                    stats.warnings,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    self.data[2 + i * 4..][..4].copy_from_slice(&value.to_be_bytes());
+                }
+            }
+
+            ViaKeyboardValueId::DEFINITION => {
+                let offset = u16::from_be_bytes([self.data[1], self.data[2]]) as usize;
+                self.data[3..5].copy_from_slice(&(DEFINITION.len() as u16).to_be_bytes());
+
+                if let Some(chunk) = DEFINITION.get(offset..) {
+                    let len = chunk.len().min(self.data.len() - 5);
+                    self.data[5..][..len].copy_from_slice(&chunk[..len]);
+                }
             }
 
             _ => {
                 self.set_invalid();
-                defmt::warn!("Invalid via keyboard value requested: {=u8:04X}", value_id);
+                log::warn!("Invalid via keyboard value requested: {:#04X}", value_id);
             }
         }
     }
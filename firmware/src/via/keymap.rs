@@ -1,7 +1,7 @@
 use keycode::Keycode;
 use zerocopy::{FromBytes, Immutable, IntoBytes, big_endian};
 
-use crate::userdata::keymap::Keymap;
+use crate::userdata::keymap::{Keymap, LAYER_COUNT};
 
 #[derive(PartialEq, Eq, FromBytes, IntoBytes, Immutable)]
 #[repr(C)]
@@ -19,9 +19,19 @@ pub struct KeymapBuffer {
     pub fx1: big_endian::U16,
     pub fx2: big_endian::U16,
     _unused2: [big_endian::U16; 2],
+
+    pub aux1: big_endian::U16,
+    pub aux2: big_endian::U16,
+    pub aux3: big_endian::U16,
+    pub aux4: big_endian::U16,
 }
 
 impl KeymapBuffer {
+    /// Buffers of all layers, contiguous in layer order like QMK
+    pub fn from_keymaps(keymaps: &[Keymap; LAYER_COUNT]) -> [Self; LAYER_COUNT] {
+        core::array::from_fn(|layer| Self::from_keymap(&keymaps[layer]))
+    }
+
     pub fn from_keymap(keymap: &Keymap) -> Self {
         Self {
             _unused0: big_endian::U16::ZERO,
@@ -35,6 +45,11 @@ impl KeymapBuffer {
             fx1: big_endian::U16::new(keymap.fx1.0),
             fx2: big_endian::U16::new(keymap.fx2.0),
             _unused2: [big_endian::U16::ZERO; 2],
+
+            aux1: big_endian::U16::new(keymap.aux1.0),
+            aux2: big_endian::U16::new(keymap.aux2.0),
+            aux3: big_endian::U16::new(keymap.aux3.0),
+            aux4: big_endian::U16::new(keymap.aux4.0),
         }
     }
 
@@ -48,6 +63,11 @@ impl KeymapBuffer {
 
         map.fx1 = Keycode::from(self.fx1.get());
         map.fx2 = Keycode::from(self.fx2.get());
+
+        map.aux1 = Keycode::from(self.aux1.get());
+        map.aux2 = Keycode::from(self.aux2.get());
+        map.aux3 = Keycode::from(self.aux3.get());
+        map.aux4 = Keycode::from(self.aux4.get());
     }
 }
 
@@ -64,6 +84,11 @@ pub fn get_keymap_keycode(map: &Keymap, row: u8, col: u8) -> Option<Keycode> {
         (2, 0) => Some(map.fx1),
         (2, 1) => Some(map.fx2),
 
+        (3, 0) => Some(map.aux1),
+        (3, 1) => Some(map.aux2),
+        (3, 2) => Some(map.aux3),
+        (3, 3) => Some(map.aux4),
+
         _ => None,
     }
 }
@@ -95,6 +120,19 @@ pub fn set_keymap_keycode(map: &mut Keymap, row: u8, col: u8, code: Keycode) {
             map.fx2 = code;
         }
 
+        (3, 0) => {
+            map.aux1 = code;
+        }
+        (3, 1) => {
+            map.aux2 = code;
+        }
+        (3, 2) => {
+            map.aux3 = code;
+        }
+        (3, 3) => {
+            map.aux4 = code;
+        }
+
         _ => {}
     }
 }
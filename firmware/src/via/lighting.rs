@@ -0,0 +1,68 @@
+use crate::{
+    log,
+    userdata::{self, led::LedEffect},
+    via::ViaCmd,
+};
+
+/// Via backlight value ids from
+/// https://github.com/qmk/qmk_firmware/blob/acbeec29dab5331fe914f35a53d6b43325881e4d/quantum/via.h#L107
+struct BacklightValueId;
+impl BacklightValueId {
+    pub const BRIGHTNESS: u8 = 0x01;
+    pub const EFFECT: u8 = 0x02;
+}
+
+impl ViaCmd<'_> {
+    pub fn read_backlight_get_value(self) {
+        let value_id = self.data[1];
+        let led = userdata::get(|data| data.led);
+
+        match value_id {
+            BacklightValueId::BRIGHTNESS => {
+                self.data[2] = led.brightness;
+            }
+
+            BacklightValueId::EFFECT => {
+                self.data[2] = led.effect as u8;
+            }
+
+            _ => {
+                self.set_invalid();
+            }
+        }
+    }
+
+    pub fn read_backlight_set_value(self) {
+        let value_id = self.data[1];
+        let value = self.data[2];
+
+        match value_id {
+            BacklightValueId::BRIGHTNESS => {
+                userdata::update(|data| {
+                    data.led.brightness = value;
+                });
+                log::info!("LED brightness set to {}", value);
+            }
+
+            BacklightValueId::EFFECT => {
+                let effect = match value {
+                    0 => LedEffect::Static,
+                    1 => LedEffect::Breathing,
+                    _ => {
+                        self.set_invalid();
+                        return;
+                    }
+                };
+
+                userdata::update(|data| {
+                    data.led.effect = effect;
+                });
+                log::info!("LED effect set to {}", value);
+            }
+
+            _ => {
+                self.set_invalid();
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Vial protocol over the Via raw HID channel.
+//! Command layouts from
+//! https://github.com/vial-kb/vial-qmk/blob/vial/quantum/vial.c
+
+use embassy_time::{Duration, Instant};
+use keycode::Keycode;
+use portable_atomic::{AtomicBool, AtomicU8, Ordering};
+use zerocopy::{FromBytes, IntoBytes};
+
+use crate::{
+    input::{CURRENT_INPUT, reader::button::ButtonId},
+    log,
+    userdata::{self, dynamic::DynamicEntries},
+    via::{
+        DEFINITION, ViaCmd,
+        encoder::{get_encoder_keycode, set_encoder_keycode},
+    },
+};
+
+struct VialCmdId;
+impl VialCmdId {
+    pub const GET_KEYBOARD_ID: u8 = 0x00;
+    pub const GET_SIZE: u8 = 0x01;
+    pub const GET_DEFINITION: u8 = 0x02;
+    pub const GET_ENCODER: u8 = 0x03;
+    pub const SET_ENCODER: u8 = 0x04;
+    pub const GET_UNLOCK_STATUS: u8 = 0x05;
+    pub const UNLOCK_START: u8 = 0x06;
+    pub const UNLOCK_POLL: u8 = 0x07;
+    pub const LOCK: u8 = 0x08;
+    pub const QMK_SETTINGS_QUERY: u8 = 0x09;
+    pub const DYNAMIC_ENTRY_OP: u8 = 0x0D;
+}
+
+struct DynamicEntryOp;
+impl DynamicEntryOp {
+    pub const GET_NUMBER_OF_ENTRIES: u8 = 0x00;
+    pub const TAP_DANCE_GET: u8 = 0x01;
+    pub const TAP_DANCE_SET: u8 = 0x02;
+    pub const COMBO_GET: u8 = 0x03;
+    pub const COMBO_SET: u8 = 0x04;
+    pub const KEY_OVERRIDE_GET: u8 = 0x05;
+    pub const KEY_OVERRIDE_SET: u8 = 0x06;
+}
+
+const VIAL_PROTOCOL_VERSION: u32 = 6;
+const KEYBOARD_UID: [u8; 8] = [0x5d, 0x1a, 0x3c, 0x0e, 0x9b, 0x27, 0xf4, 0x61];
+
+/// Keys to hold for unlock, FX-L and FX-R as (row, col)
+const UNLOCK_KEYS: [(u8, u8); 2] = [(2, 0), (2, 1)];
+/// Unlock polls with keys held, at least [`UNLOCK_POLL_INTERVAL`] apart
+const UNLOCK_COUNTER_MAX: u8 = 50;
+const UNLOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Starts locked on every boot. Unlocked by holding [`UNLOCK_KEYS`] while Vial polls,
+/// and stays unlocked until locked by Vial or rebooted.
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+static UNLOCK_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static UNLOCK_COUNTER: AtomicU8 = AtomicU8::new(UNLOCK_COUNTER_MAX);
+static UNLOCK_LAST_POLL: critical_section::Mutex<core::cell::Cell<Instant>> =
+    critical_section::Mutex::new(core::cell::Cell::new(Instant::MIN));
+
+/// Commands changing firmware state beyond keymap require unlock
+pub fn unlocked() -> bool {
+    UNLOCKED.load(Ordering::Relaxed)
+}
+
+impl ViaCmd<'_> {
+    /// Vial responses start at the command id byte
+    fn vial_response(&mut self, f: impl FnOnce(&mut [u8; 32])) {
+        let mut msg = [0_u8; 32];
+        msg[0] = *self.id;
+        msg[1..].copy_from_slice(&self.data[..31]);
+        f(&mut msg);
+
+        *self.id = msg[0];
+        self.data[..31].copy_from_slice(&msg[1..]);
+    }
+
+    pub fn read_vial_cmd(mut self) {
+        let cmd_id = self.data[0];
+
+        match cmd_id {
+            VialCmdId::GET_KEYBOARD_ID => self.vial_response(|msg| {
+                msg[..4].copy_from_slice(&VIAL_PROTOCOL_VERSION.to_le_bytes());
+                msg[4..12].copy_from_slice(&KEYBOARD_UID);
+                msg[12] = 0;
+                log::info!("Vial connected.");
+            }),
+
+            VialCmdId::GET_SIZE => self.vial_response(|msg| {
+                msg[..4].copy_from_slice(&(DEFINITION.len() as u32).to_le_bytes());
+            }),
+
+            VialCmdId::GET_DEFINITION => self.vial_response(|msg| {
+                let page = u16::from_le_bytes([msg[2], msg[3]]) as usize;
+                if let Some(chunk) = DEFINITION.chunks(msg.len()).nth(page) {
+                    msg[..chunk.len()].copy_from_slice(chunk);
+                }
+            }),
+
+            VialCmdId::GET_ENCODER => self.vial_response(|msg| {
+                let (layer, encoder_id) = (msg[2], msg[3]);
+                let ccw = get_encoder_keycode(layer, encoder_id, false).unwrap_or_default();
+                let cw = get_encoder_keycode(layer, encoder_id, true).unwrap_or_default();
+                msg[..2].copy_from_slice(&ccw.0.to_be_bytes());
+                msg[2..4].copy_from_slice(&cw.0.to_be_bytes());
+            }),
+
+            VialCmdId::SET_ENCODER => self.vial_response(|msg| {
+                let key = Keycode::from(u16::from_be_bytes([msg[5], msg[6]]));
+                set_encoder_keycode(msg[2], msg[3], msg[4] != 0, key);
+                userdata::save();
+            }),
+
+            VialCmdId::GET_UNLOCK_STATUS => self.vial_response(|msg| {
+                msg.fill(0xFF);
+                msg[0] = unlocked() as u8;
+                msg[1] = UNLOCK_IN_PROGRESS.load(Ordering::Relaxed) as u8;
+                for (i, (row, col)) in UNLOCK_KEYS.into_iter().enumerate() {
+                    msg[2 + i * 2] = row;
+                    msg[3 + i * 2] = col;
+                }
+            }),
+
+            VialCmdId::UNLOCK_START => {
+                UNLOCK_IN_PROGRESS.store(true, Ordering::Relaxed);
+                UNLOCK_COUNTER.store(UNLOCK_COUNTER_MAX, Ordering::Relaxed);
+                log::info!("Vial unlock started.");
+            }
+
+            VialCmdId::UNLOCK_POLL => self.vial_response(|msg| {
+                poll_unlock();
+                msg[0] = unlocked() as u8;
+                msg[1] = UNLOCK_IN_PROGRESS.load(Ordering::Relaxed) as u8;
+                msg[2] = UNLOCK_COUNTER.load(Ordering::Relaxed);
+            }),
+
+            VialCmdId::LOCK => {
+                UNLOCKED.store(false, Ordering::Relaxed);
+                log::info!("Locked by Vial.");
+            }
+
+            // QMK settings are not supported
+            VialCmdId::QMK_SETTINGS_QUERY => self.vial_response(|msg| msg.fill(0xFF)),
+
+            VialCmdId::DYNAMIC_ENTRY_OP => self.vial_response(dynamic_entry_op),
+
+            _ => {
+                log::warn!("Invalid vial command received: {:#04X}", cmd_id);
+                self.set_invalid();
+            }
+        }
+    }
+}
+
+fn poll_unlock() {
+    if !UNLOCK_IN_PROGRESS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let buttons = CURRENT_INPUT.borrow().get().buttons;
+    if !(buttons.pressed(ButtonId::FX1) && buttons.pressed(ButtonId::FX2)) {
+        UNLOCK_COUNTER.store(UNLOCK_COUNTER_MAX, Ordering::Relaxed);
+        return;
+    }
+
+    let now = Instant::now();
+    let polled = critical_section::with(|cs| {
+        let last = UNLOCK_LAST_POLL.borrow(cs);
+        let polled = now.duration_since(last.get()) >= UNLOCK_POLL_INTERVAL;
+        if polled {
+            last.set(now);
+        }
+        polled
+    });
+    if !polled {
+        return;
+    }
+
+    if UNLOCK_COUNTER.fetch_sub(1, Ordering::Relaxed) <= 1 {
+        UNLOCK_IN_PROGRESS.store(false, Ordering::Relaxed);
+        UNLOCKED.store(true, Ordering::Relaxed);
+        log::info!("Unlocked by Vial.");
+    }
+}
+
+/// Read or write entry at index as raw bytes. Returns status, 0 on success.
+fn entry_op<T: FromBytes + IntoBytes + zerocopy::Immutable>(
+    msg: &mut [u8; 32],
+    entries: impl FnOnce(&mut DynamicEntries) -> &mut [T],
+    set: bool,
+) -> u8 {
+    let index = msg[3] as usize;
+    let len = size_of::<T>();
+
+    if set {
+        let Ok(entry) = T::read_from_bytes(&msg[4..][..len]) else {
+            return 0xFF;
+        };
+
+        let found = userdata::update(|data| {
+            let Some(slot) = entries(&mut data.dynamic).get_mut(index) else {
+                return false;
+            };
+            *slot = entry;
+            true
+        });
+        if !found {
+            return 0xFF;
+        }
+
+        userdata::save();
+        0
+    } else {
+        let mut dynamic = userdata::get(|data| data.dynamic.clone());
+        let Some(entry) = entries(&mut dynamic).get(index) else {
+            return 0xFF;
+        };
+
+        msg[1..][..len].copy_from_slice(entry.as_bytes());
+        0
+    }
+}
+
+fn dynamic_entry_op(msg: &mut [u8; 32]) {
+    let status = match msg[2] {
+        DynamicEntryOp::GET_NUMBER_OF_ENTRIES => {
+            let entries = DynamicEntries::DEFAULT;
+            msg.fill(0);
+            msg[0] = entries.tap_dance.len() as u8;
+            msg[1] = entries.combo.len() as u8;
+            msg[2] = entries.key_override.len() as u8;
+            return;
+        }
+
+        DynamicEntryOp::TAP_DANCE_GET => entry_op(msg, |e| &mut e.tap_dance, false),
+        DynamicEntryOp::TAP_DANCE_SET => entry_op(msg, |e| &mut e.tap_dance, true),
+        DynamicEntryOp::COMBO_GET => entry_op(msg, |e| &mut e.combo, false),
+        DynamicEntryOp::COMBO_SET => entry_op(msg, |e| &mut e.combo, true),
+        DynamicEntryOp::KEY_OVERRIDE_GET => entry_op(msg, |e| &mut e.key_override, false),
+        DynamicEntryOp::KEY_OVERRIDE_SET => entry_op(msg, |e| &mut e.key_override, true),
+
+        op => {
+            log::warn!("Invalid vial dynamic entry op: {:#04X}", op);
+            0xFF
+        }
+    };
+
+    msg[0] = status;
+}
@@ -0,0 +1,107 @@
+//! Hardware watchdog supervised from core 1.
+//! Tasks on core 0 report heartbeats, and the watchdog is fed only while all of them are alive.
+//! USB heartbeat comes from written reports, and is only expected while a report write is
+//! in progress on a configured, awake bus.
+
+use embassy_rp::{Peri, peripherals::WATCHDOG, watchdog::Watchdog};
+use embassy_time::{Duration, Instant, Ticker};
+use portable_atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::{crash, log};
+
+/// Long enough to cover flash sector erase while core 1 is paused
+const WATCHDOG_PERIOD: Duration = Duration::from_secs(1);
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// Heartbeat missing for this long is treated as a stall
+const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Heartbeat {
+    /// Input read loop
+    Input = 0,
+    /// Input report writes
+    Usb = 1,
+}
+
+impl Heartbeat {
+    /// Names in bit order
+    const NAMES: [&str; 2] = ["input", "usb"];
+}
+
+static HEARTBEATS: AtomicU8 = AtomicU8::new(0);
+
+/// Report writes in progress
+static USB_WRITES: AtomicU8 = AtomicU8::new(0);
+static USB_CONFIGURED: AtomicBool = AtomicBool::new(false);
+static USB_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// Report that the task is alive
+#[inline]
+pub fn heartbeat(source: Heartbeat) {
+    HEARTBEATS.fetch_or(1 << source as u8, Ordering::Relaxed);
+}
+
+/// Report write in progress until dropped
+pub struct UsbWrite(());
+
+impl UsbWrite {
+    pub fn start() -> Self {
+        USB_WRITES.fetch_add(1, Ordering::Relaxed);
+        Self(())
+    }
+}
+
+impl Drop for UsbWrite {
+    fn drop(&mut self) {
+        USB_WRITES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn set_usb_configured(configured: bool) {
+    USB_CONFIGURED.store(configured, Ordering::Relaxed);
+}
+
+pub fn set_usb_suspended(suspended: bool) {
+    USB_SUSPENDED.store(suspended, Ordering::Relaxed);
+}
+
+/// Writes wait for the host, so USB heartbeat is expected
+fn usb_expected() -> bool {
+    USB_WRITES.load(Ordering::Relaxed) != 0
+        && USB_CONFIGURED.load(Ordering::Relaxed)
+        && !USB_SUSPENDED.load(Ordering::Relaxed)
+}
+
+#[embassy_executor::task]
+pub async fn watchdog_task(watchdog: Peri<'static, WATCHDOG>) {
+    let mut watchdog = Watchdog::new(watchdog);
+    watchdog.pause_on_debug(true);
+    watchdog.start(WATCHDOG_PERIOD);
+
+    let now = Instant::now();
+    let mut last_seen = [now; Heartbeat::NAMES.len()];
+
+    let mut ticker = Ticker::every(CHECK_INTERVAL);
+    loop {
+        ticker.next().await;
+
+        let now = Instant::now();
+        let beats = HEARTBEATS.swap(0, Ordering::Relaxed);
+        for (i, name) in Heartbeat::NAMES.iter().enumerate() {
+            let idle = i == Heartbeat::Usb as usize && !usb_expected();
+            if beats & (1 << i) != 0 || idle {
+                last_seen[i] = now;
+                continue;
+            }
+
+            if now.duration_since(last_seen[i]) >= STALL_TIMEOUT {
+                log::error!("Watchdog: {} task stalled. Resetting...", name);
+                crash::record(format_args!("watchdog: {} task stalled", name));
+                crash::reset();
+            }
+        }
+
+        watchdog.feed();
+    }
+}
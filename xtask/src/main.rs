@@ -1,32 +1,190 @@
-use core::error::Error;
-use std::{env, process::Command};
+use core::{error::Error, time::Duration};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    thread,
+};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use defmt_decoder::{DecodeError, Table};
+use serde_json::json;
+
+/// Firmware ELF built by `cargo xtask flash --release` for RP235x
+const DEFAULT_ELF: &str = "target/thumbv8m.main-none-eabihf/release/firmware";
+
+/// Time to wait for console output
+const CONSOLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Chip {
+    Rp235xa,
+    Rp2040,
+}
+
+impl Chip {
+    /// Cargo target and firmware feature of chip
+    fn target(self) -> (&'static str, &'static str) {
+        match self {
+            Chip::Rp235xa => ("thumbv8m.main-none-eabihf", "rp235xa"),
+            Chip::Rp2040 => ("thumbv6m-none-eabi", "rp2040"),
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 enum Cmd {
     #[command(about = "Build and flash firmware")]
     Flash {
+        /// Chip of the controller board
+        #[arg(long, value_enum, default_value_t = Chip::Rp235xa)]
+        chip: Chip,
+
         #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
         cargo_args: Vec<String>,
     },
+
+    #[command(about = "Decode captured defmt log stream")]
+    Decode {
+        /// Captured raw bytes from logger serial port
+        input: PathBuf,
+
+        /// Firmware ELF the logs were captured from
+        #[arg(long, default_value = DEFAULT_ELF)]
+        elf: PathBuf,
+    },
+
+    #[command(about = "Print button press counts and knob turns of connected controller")]
+    Usage {
+        /// Console serial port (e.g. /dev/ttyACM1, COM4)
+        port: PathBuf,
+    },
+
+    #[command(about = "Print Via custom keycodes and menus generated from firmware sources")]
+    ViaJson {
+        /// Write whole generated definition to firmware/via.json instead
+        #[arg(long)]
+        write: bool,
+    },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     match Cmd::parse() {
-        Cmd::Flash { cargo_args } => {
+        Cmd::Flash { chip, cargo_args } => {
+            let (target, feature) = chip.target();
             cargo_cmd()
                 .current_dir("./firmware")
-                .args(["run", "-p", "firmware"])
+                .args(["run", "-p", "firmware", "--target", target])
+                .args(["--no-default-features", "--features", feature])
                 .args(cargo_args)
                 .status()?;
         }
+
+        Cmd::Decode { input, elf } => decode(&input, &elf)?,
+
+        Cmd::Usage { port } => print!("{}", console_cmd(&port, "usage")?),
+
+        Cmd::ViaJson { write } => {
+            let def = via_gen::generate("./firmware")?;
+            if write {
+                fs::write("./firmware/via.json", format!("{def:#}"))?;
+            } else {
+                println!(
+                    "{:#}",
+                    json!({
+                        "customKeycodes": def["customKeycodes"],
+                        "menus": def["menus"],
+                    })
+                );
+            }
+        }
     }
 
     Ok(())
 }
 
+fn decode(input: &PathBuf, elf: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let elf = fs::read(elf)?;
+    let table = Table::parse(&elf)?.ok_or("ELF does not contain defmt data")?;
+
+    let mut decoder = table.new_stream_decoder();
+    decoder.received(&fs::read(input)?);
+    loop {
+        match decoder.decode() {
+            Ok(frame) => println!("{}", frame.display(false)),
+
+            Err(DecodeError::UnexpectedEof) => break,
+
+            Err(DecodeError::Malformed) => {
+                // Captures may start or end in the middle of a frame
+                if table.encoding().can_recover() {
+                    eprintln!("Skipped malformed frame");
+                } else {
+                    return Err("Malformed frame".into());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run command on serial console and return its output
+fn console_cmd(port: &Path, cmd: &str) -> Result<String, Box<dyn Error>> {
+    // Disable line buffering and echo of host tty
+    #[cfg(unix)]
+    {
+        let flag = if cfg!(target_os = "macos") {
+            "-f"
+        } else {
+            "-F"
+        };
+        let status = Command::new("stty")
+            .arg(flag)
+            .arg(port)
+            .args(["raw", "-echo"])
+            .status()?;
+        if !status.success() {
+            return Err("Failed to configure serial port".into());
+        }
+    }
+
+    let mut serial = OpenOptions::new().read(true).write(true).open(port)?;
+    let mut reader = serial.try_clone()?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0; 256];
+        while let Ok(len @ 1..) = reader.read(&mut buf) {
+            if sender.send(buf[..len].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Terminate partially typed line first
+    serial.write_all(format!("\r{cmd}\r").as_bytes())?;
+
+    // Output is between echoed command and next prompt
+    let echo = format!("{cmd}\r\n");
+    let mut output = String::new();
+    loop {
+        let chunk = receiver
+            .recv_timeout(CONSOLE_TIMEOUT)
+            .map_err(|_| "Console did not respond")?;
+        output.push_str(&String::from_utf8_lossy(&chunk));
+
+        if let Some(start) = output.find(&echo).map(|i| i + echo.len())
+            && let Some(len) = output[start..].find("> ")
+        {
+            return Ok(output[start..][..len].replace("\r\n", "\n"));
+        }
+    }
+}
+
 fn cargo_cmd() -> Command {
     let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
     Command::new(cargo)